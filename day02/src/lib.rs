@@ -1,6 +1,7 @@
 use anyhow::{Result, bail};
+use std::collections::HashSet;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct GameData {
     pub red: usize,
     pub green: usize,
@@ -45,6 +46,16 @@ impl GameData {
     pub fn power(&self) -> usize {
         self.red * self.green * self.blue
     }
+
+    pub fn total(&self) -> usize {
+        self.red + self.green + self.blue
+    }
+
+    pub fn contains(&self, other: &GameData) -> bool {
+        other.red <= self.red &&
+        other.green <= self.green &&
+        other.blue <= self.blue
+    }
 }
 
 #[derive(Debug)]
@@ -56,10 +67,7 @@ pub struct Game {
 impl Game {
     pub fn is_possible(&self, reference: &GameData) -> bool {
         self.draws.iter()
-            .all(|draw|
-                 draw.blue <= reference.blue &&
-                 draw.red <= reference.red &&
-                 draw.green <= reference.green)
+            .all(|draw| reference.contains(draw))
     }
 
     pub fn minimal_set(&self) -> GameData {
@@ -85,9 +93,117 @@ pub fn parse_line(st: &str) -> Game {
     }
 }
 
+/// The games constraining one colour the most: the largest count seen for
+/// it, and the IDs of every game that drew that many.
+#[derive(Debug, Default, PartialEq)]
+pub struct ColorConstraint {
+    pub count: usize,
+    pub games: Vec<usize>,
+}
+
+impl ColorConstraint {
+    fn update(&mut self, count: usize, id: usize) {
+        if count > self.count {
+            self.count = count;
+            self.games = vec![id];
+        } else if count == self.count && count > 0 {
+            self.games.push(id);
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Constraints {
+    pub red: ColorConstraint,
+    pub green: ColorConstraint,
+    pub blue: ColorConstraint,
+}
+
+/// A whole log of games, to reason about the bags that could have produced it
+#[derive(Debug)]
+pub struct GameLog {
+    games: Vec<Game>,
+}
+
+impl GameLog {
+    pub fn new(games: Vec<Game>) -> Self {
+        GameLog { games }
+    }
+
+    pub fn games(&self) -> &[Game] {
+        &self.games
+    }
+
+    /// Smallest bag for which every game in the log is possible
+    pub fn minimal_bag(&self) -> GameData {
+        self.games
+            .iter()
+            .fold(
+                GameData::new(0, 0, 0),
+                |acc, game| acc.merge_larger(&game.minimal_set()))
+    }
+
+    /// Lists every bag holding at most `budget` cubes for which all the
+    /// games in `ids` are possible. Fails if any of the IDs is not in the log.
+    pub fn feasible_bags(&self, budget: usize, ids: &[usize]) -> Result<Vec<GameData>> {
+        let known = self.games.iter().map(|game| game.id).collect::<HashSet<_>>();
+        if let Some(id) = ids.iter().find(|id| !known.contains(id)) {
+            bail!("Unknown game ID {id}")
+        }
+
+        let wanted = ids.iter().collect::<HashSet<_>>();
+        let minimal = self.games
+            .iter()
+            .filter(|game| wanted.contains(&game.id))
+            .fold(
+                GameData::new(0, 0, 0),
+                |acc, game| acc.merge_larger(&game.minimal_set()));
+
+        let mut bags = vec![];
+        if minimal.total() > budget {
+            return Ok(bags);
+        }
+
+        for red in minimal.red..=(budget - minimal.green - minimal.blue) {
+            for green in minimal.green..=(budget - red - minimal.blue) {
+                for blue in minimal.blue..=(budget - red - green) {
+                    bags.push(GameData::new(red, green, blue));
+                }
+            }
+        }
+
+        Ok(bags)
+    }
+
+    /// For each colour, reports the games that require the most cubes of it
+    pub fn tightest_constraints(&self) -> Constraints {
+        let mut constraints = Constraints::default();
+
+        for game in self.games.iter() {
+            let minimal = game.minimal_set();
+            constraints.red.update(minimal.red, game.id);
+            constraints.green.update(minimal.green, game.id);
+            constraints.blue.update(minimal.blue, game.id);
+        }
+
+        constraints
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::GameData;
+    use crate::{GameData, GameLog, parse_line};
+
+    const SAMPLE: &str = "\
+Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
+
+    fn sample_log() -> GameLog {
+        GameLog::new(SAMPLE.lines().map(parse_line).collect())
+    }
 
     #[test]
     fn build_data_from_string() {
@@ -104,4 +220,36 @@ mod tests {
             Some(GameData::new(0, 2, 0))
             );
     }
+
+    #[test]
+    fn minimal_bag_for_log() {
+        assert_eq!(sample_log().minimal_bag(), GameData::new(20, 13, 15));
+    }
+
+    #[test]
+    fn feasible_bags_within_budget() {
+        let log = sample_log();
+
+        // Games 1 & 2 need at least 4 red, 3 green, 6 blue
+        let bags = log.feasible_bags(15, &[1, 2]).unwrap();
+        assert_eq!(bags.len(), 10);
+        assert!(bags.contains(&GameData::new(4, 3, 6)));
+        assert!(bags.contains(&GameData::new(4, 3, 8)));
+        assert!(bags.contains(&GameData::new(6, 3, 6)));
+        assert!(bags.iter().all(|bag| bag.total() <= 15));
+        assert!(bags.iter().all(|bag| log.games()[0].is_possible(bag) && log.games()[1].is_possible(bag)));
+
+        assert_eq!(log.feasible_bags(12, &[1, 2]).unwrap(), vec![]);
+        assert_eq!(log.feasible_bags(13, &[1, 2]).unwrap(), vec![GameData::new(4, 3, 6)]);
+        assert_eq!(log.feasible_bags(48, &[]).unwrap().len(), 20825);
+        assert!(log.feasible_bags(20, &[6]).is_err());
+    }
+
+    #[test]
+    fn tightest_constraints() {
+        let constraints = sample_log().tightest_constraints();
+        assert_eq!((constraints.red.count, constraints.red.games), (20, vec![3]));
+        assert_eq!((constraints.green.count, constraints.green.games), (13, vec![3]));
+        assert_eq!((constraints.blue.count, constraints.blue.games), (15, vec![4]));
+    }
 }
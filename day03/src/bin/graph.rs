@@ -0,0 +1,25 @@
+use std::env;
+use std::io::stdin;
+use std::process::exit;
+
+use day03::{parse_line, Adjacency, Schematic};
+use day03::graph::Graph;
+
+fn main() {
+    let format = env::args().nth(1).unwrap_or("dot".to_string());
+
+    let schematic = stdin().lines()
+        .enumerate()
+        .flat_map(|(row, line)| parse_line(&line.unwrap(), row as i32))
+        .collect::<Schematic>();
+    let graph = Graph::new(&schematic, Adjacency::Moore);
+
+    match format.as_str() {
+        "dot" => print!("{}", graph.to_dot()),
+        "json" => println!("{}", graph.to_json()),
+        other => {
+            eprintln!("Unknown format {other}. Use either 'dot' or 'json'");
+            exit(1);
+        }
+    }
+}
@@ -1,28 +1,14 @@
 use std::io::stdin;
 
-use day03::{parse_line, SchematicObject, Symbol, Number};
+use day03::{parse_line, Adjacency, Schematic};
 
 fn main() {
-    let mut lines = stdin().lines().enumerate();
-    let mut symbols: Vec<Symbol> = vec![];
-    let mut numbers: Vec<Number> = vec![];
+    let schematic = stdin().lines()
+        .enumerate()
+        .flat_map(|(row, line)| parse_line(&line.unwrap(), row as i32))
+        .collect::<Schematic>();
 
-    while let Some((row, Ok(line))) = lines.next() {
-        let row = row as i32;
-
-        for object in parse_line(&line, row as i32) {
-            match object {
-                SchematicObject::Numeric(num) => {
-                    numbers.push(num)
-                }
-                SchematicObject::Symbolic(sym) => {
-                    symbols.push(sym);
-                }
-            }
-        }
-    }
-
-    let parts = numbers.iter().filter(|number| number.is_part_num(&symbols));
+    let parts = schematic.part_numbers(Adjacency::Moore);
 
     println!("The sum of all the part numbers is: {}", parts.map(|part| part.value).sum::<u32>());
 }
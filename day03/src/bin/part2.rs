@@ -1,32 +1,16 @@
 use std::io::stdin;
 
-use day03::{parse_line, SchematicObject, Symbol, Number};
+use day03::{parse_line, GearRule, Schematic};
 
 fn main() {
-    let mut lines = stdin().lines().enumerate();
-    let mut maybe_gears: Vec<Symbol> = vec![];
-    let mut numbers: Vec<Number> = vec![];
+    let schematic = stdin().lines()
+        .enumerate()
+        .flat_map(|(row, line)| parse_line(&line.unwrap(), row as i32))
+        .collect::<Schematic>();
 
-    while let Some((row, Ok(line))) = lines.next() {
-        let row = row as i32;
+    let ratios = schematic.gears(&GearRule::default())
+        .into_iter()
+        .map(|(_, ratio)| ratio);
 
-        for object in parse_line(&line, row as i32) {
-            match object {
-                SchematicObject::Numeric(num) => {
-                    numbers.push(num)
-                }
-                SchematicObject::Symbolic(sym) => {
-                    if sym.maybe_gear() {
-                        maybe_gears.push(sym);
-                    }
-                }
-            }
-        }
-    }
-
-    numbers.retain(|number| number.is_part_num(&maybe_gears));
-    let ratios = maybe_gears.iter()
-        .filter_map(|mg| mg.gear_ratio(&numbers));
-
-    println!("The added ratios are: {}", ratios.sum::<u32>());
+    println!("The added ratios are: {}", ratios.sum::<u64>());
 }
@@ -0,0 +1,174 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use crate::{Adjacency, Number, Schematic, Symbol};
+
+// Both DOT and JSON strings need quotes and backslashes escaped
+fn escaped(ch: char) -> String {
+    match ch {
+        '"' | '\\' => format!("\\{ch}"),
+        c => c.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Node {
+    Number(usize),
+    Symbol(usize),
+}
+
+/// A set of numbers and symbols linked to each other through adjacency
+#[derive(Debug, PartialEq)]
+pub struct Component<'a> {
+    pub numbers: Vec<&'a Number>,
+    pub symbols: Vec<&'a Symbol>,
+}
+
+/// Bipartite graph linking each number in a schematic to its adjacent symbols
+#[derive(Debug)]
+pub struct Graph<'a> {
+    schematic: &'a Schematic,
+    number_links: Vec<Vec<usize>>,
+    symbol_links: Vec<Vec<usize>>,
+}
+
+impl<'a> Graph<'a> {
+    pub fn new(schematic: &'a Schematic, adjacency: Adjacency) -> Self {
+        let mut number_links = vec![vec![]; schematic.numbers.len()];
+        let mut symbol_links = vec![vec![]; schematic.symbols.len()];
+
+        for (num_idx, number) in schematic.numbers.iter().enumerate() {
+            for cell in number.coords.perimeter(adjacency) {
+                if let Some(&sym_idx) = schematic.symbol_at.get(&cell) {
+                    number_links[num_idx].push(sym_idx);
+                    symbol_links[sym_idx].push(num_idx);
+                }
+            }
+        }
+
+        Graph {
+            schematic,
+            number_links,
+            symbol_links,
+        }
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item=(&'a Number, &'a Symbol)> + '_ {
+        self.number_links.iter()
+            .enumerate()
+            .flat_map(move |(num_idx, links)| links.iter()
+                      .map(move |&sym_idx| (&self.schematic.numbers[num_idx], &self.schematic.symbols[sym_idx])))
+    }
+
+    /// Returns the connected components of the graph, including the ones made
+    /// of a single number or symbol
+    pub fn components(&self) -> Vec<Component<'a>> {
+        let mut seen_numbers = vec![false; self.number_links.len()];
+        let mut seen_symbols = vec![false; self.symbol_links.len()];
+        let starts = (0..self.number_links.len()).map(Node::Number)
+            .chain((0..self.symbol_links.len()).map(Node::Symbol));
+
+        let mut components = vec![];
+        for start in starts {
+            let seen = match start {
+                Node::Number(idx) => &mut seen_numbers[idx],
+                Node::Symbol(idx) => &mut seen_symbols[idx],
+            };
+            if *seen {
+                continue
+            }
+            *seen = true;
+
+            let mut numbers = vec![];
+            let mut symbols = vec![];
+            let mut queue = VecDeque::from([start]);
+            while let Some(node) = queue.pop_front() {
+                match node {
+                    Node::Number(idx) => {
+                        numbers.push(idx);
+                        for &next in self.number_links[idx].iter() {
+                            if !seen_symbols[next] {
+                                seen_symbols[next] = true;
+                                queue.push_back(Node::Symbol(next));
+                            }
+                        }
+                    }
+                    Node::Symbol(idx) => {
+                        symbols.push(idx);
+                        for &next in self.symbol_links[idx].iter() {
+                            if !seen_numbers[next] {
+                                seen_numbers[next] = true;
+                                queue.push_back(Node::Number(next));
+                            }
+                        }
+                    }
+                }
+            }
+            numbers.sort();
+            symbols.sort();
+
+            components.push(Component {
+                numbers: numbers.into_iter().map(|idx| &self.schematic.numbers[idx]).collect(),
+                symbols: symbols.into_iter().map(|idx| &self.schematic.symbols[idx]).collect(),
+            });
+        }
+
+        components
+    }
+
+    /// Numbers that are not adjacent to any symbol
+    pub fn isolated_numbers(&self) -> Vec<&'a Number> {
+        self.number_links.iter()
+            .enumerate()
+            .filter(|(_, links)| links.is_empty())
+            .map(|(idx, _)| &self.schematic.numbers[idx])
+            .collect()
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "graph {{").unwrap();
+        for (idx, number) in self.schematic.numbers.iter().enumerate() {
+            let style = if self.number_links[idx].is_empty() { ", style=dashed" } else { "" };
+            writeln!(out, "\tn{idx} [label=\"{}\"{style}];", number.value).unwrap();
+        }
+        for (idx, symbol) in self.schematic.symbols.iter().enumerate() {
+            let label = escaped(symbol.ch);
+            writeln!(out, "\ts{idx} [label=\"{label}\", shape=box];").unwrap();
+        }
+        for (num_idx, links) in self.number_links.iter().enumerate() {
+            for sym_idx in links {
+                writeln!(out, "\tn{num_idx} -- s{sym_idx};").unwrap();
+            }
+        }
+        writeln!(out, "}}").unwrap();
+
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let numbers = self.schematic.numbers.iter()
+            .enumerate()
+            .map(|(idx, number)| format!(
+                    "{{\"id\":{idx},\"value\":{},\"row\":{},\"left_col\":{},\"right_col\":{}}}",
+                    number.value, number.coords.row, number.coords.left_col, number.coords.right_col))
+            .collect::<Vec<_>>();
+        let symbols = self.schematic.symbols.iter()
+            .enumerate()
+            .map(|(idx, symbol)| {
+                let label = escaped(symbol.ch);
+                format!("{{\"id\":{idx},\"symbol\":\"{label}\",\"row\":{},\"col\":{}}}",
+                        symbol.coords.row, symbol.coords.col)
+            })
+            .collect::<Vec<_>>();
+        let edges = self.number_links.iter()
+            .enumerate()
+            .flat_map(|(num_idx, links)| links.iter()
+                      .map(move |sym_idx| format!("{{\"number\":{num_idx},\"symbol\":{sym_idx}}}")))
+            .collect::<Vec<_>>();
+
+        format!("{{\"numbers\":[{}],\"symbols\":[{}],\"edges\":[{}]}}",
+                numbers.join(","), symbols.join(","), edges.join(","))
+    }
+}
@@ -1,9 +1,53 @@
-#[derive(Debug, PartialEq)]
+pub mod graph;
+
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Coords {
     col: i32,
     row: i32,
 }
 
+/// Which cells count as neighbours of a given one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Adjacency {
+    /// Up, down, left and right
+    Orthogonal,
+    /// Orthogonal neighbours plus the diagonals
+    #[default]
+    Moore,
+    /// Every cell within the given Chebyshev distance. Radius(1) is the same as Moore
+    Radius(i32),
+}
+
+impl Adjacency {
+    fn reach(&self) -> i32 {
+        match self {
+            Adjacency::Orthogonal | Adjacency::Moore => 1,
+            Adjacency::Radius(k) => *k,
+        }
+    }
+
+    fn within(&self, d_col: i32, d_row: i32) -> bool {
+        let (d_col, d_row) = (d_col.abs(), d_row.abs());
+        match self {
+            Adjacency::Orthogonal => d_col + d_row <= 1,
+            Adjacency::Moore => d_col <= 1 && d_row <= 1,
+            Adjacency::Radius(k) => d_col <= *k && d_row <= *k,
+        }
+    }
+
+    /// Cells within reach of `point`, excluding the point itself
+    fn around(&self, point: Coords) -> impl Iterator<Item=Coords> + '_ {
+        let reach = self.reach();
+        (-reach..=reach)
+            .flat_map(move |d_row| (-reach..=reach).map(move |d_col| (d_col, d_row)))
+            .filter(move |&(d_col, d_row)| (d_col, d_row) != (0, 0) && self.within(d_col, d_row))
+            .map(move |(d_col, d_row)| Coords { col: point.col + d_col, row: point.row + d_row })
+    }
+}
+
 #[derive(Debug, PartialEq)]
 struct CoordRange {
     left_col: i32,
@@ -21,8 +65,34 @@ impl CoordRange {
     }
 
     fn adjacent_to(&self, point: &Coords) -> bool {
-        (self.row - point.row).abs() < 2 &&
-            (self.left_col..=self.right_col).any(|col| (col - point.col).abs() < 2)
+        self.adjacent_with(point, Adjacency::Moore)
+    }
+
+    fn adjacent_with(&self, point: &Coords, adjacency: Adjacency) -> bool {
+        // Offset to the closest cell in the range
+        let d_col = if point.col < self.left_col {
+            point.col - self.left_col
+        } else if point.col > self.right_col {
+            point.col - self.right_col
+        } else {
+            0
+        };
+        let d_row = point.row - self.row;
+
+        (d_col, d_row) != (0, 0) && adjacency.within(d_col, d_row)
+    }
+
+    fn cells(&self) -> impl Iterator<Item=Coords> + '_ {
+        (self.left_col..=self.right_col).map(|col| Coords { col, row: self.row })
+    }
+
+    /// Cells surrounding the range that are adjacent to any of its members
+    fn perimeter(&self, adjacency: Adjacency) -> impl Iterator<Item=Coords> + '_ {
+        let reach = adjacency.reach();
+        ((self.row - reach)..=(self.row + reach))
+            .flat_map(move |row| ((self.left_col - reach)..=(self.right_col + reach))
+                      .map(move |col| Coords { col, row }))
+            .filter(move |coords| self.adjacent_with(coords, adjacency))
     }
 }
 
@@ -100,6 +170,135 @@ impl SchematicObject {
     }
 }
 
+pub type CombineFn = Box<dyn Fn(&[u32]) -> u64>;
+
+/// How neighbouring values are merged into the ratio of a gear
+pub enum Combine {
+    Product,
+    Sum,
+    Custom(CombineFn),
+}
+
+impl Combine {
+    /// None if the ratio doesn't fit in a u64
+    fn apply(&self, values: &[u32]) -> Option<u64> {
+        match self {
+            Combine::Product => values.iter().try_fold(1u64, |acc, &value| acc.checked_mul(value as u64)),
+            Combine::Sum => values.iter().try_fold(0u64, |acc, &value| acc.checked_add(value as u64)),
+            Combine::Custom(func) => Some(func(values)),
+        }
+    }
+}
+
+/// Describes what makes a symbol a gear, and how to compute its ratio. The
+/// default rule is the one from the puzzle: a '*' touching exactly two numbers,
+/// whose ratio is their product.
+pub struct GearRule {
+    pub symbols: HashSet<char>,
+    pub neighbours: RangeInclusive<usize>,
+    pub combine: Combine,
+    pub adjacency: Adjacency,
+}
+
+impl Default for GearRule {
+    fn default() -> Self {
+        GearRule {
+            symbols: HashSet::from(['*']),
+            neighbours: 2..=2,
+            combine: Combine::Product,
+            adjacency: Adjacency::Moore,
+        }
+    }
+}
+
+/// All the objects in a schematic, indexed by the cells they occupy, so that
+/// adjacency queries only need to look around the object of interest.
+#[derive(Debug, Default)]
+pub struct Schematic {
+    numbers: Vec<Number>,
+    symbols: Vec<Symbol>,
+    number_at: HashMap<Coords, usize>,
+    symbol_at: HashMap<Coords, usize>,
+}
+
+impl Schematic {
+    pub fn new() -> Self {
+        Schematic::default()
+    }
+
+    pub fn add(&mut self, object: SchematicObject) {
+        match object {
+            SchematicObject::Numeric(num) => {
+                let index = self.numbers.len();
+                self.number_at.extend(num.coords.cells().map(|cell| (cell, index)));
+                self.numbers.push(num);
+            }
+            SchematicObject::Symbolic(sym) => {
+                self.symbol_at.insert(sym.coords, self.symbols.len());
+                self.symbols.push(sym);
+            }
+        }
+    }
+
+    pub fn numbers(&self) -> &[Number] {
+        &self.numbers
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn symbols_adjacent_to<'a>(&'a self, number: &'a Number, adjacency: Adjacency) -> impl Iterator<Item=&'a Symbol> + 'a {
+        number.coords.perimeter(adjacency)
+            .filter_map(|cell| self.symbol_at.get(&cell))
+            .map(|&index| &self.symbols[index])
+    }
+
+    pub fn numbers_adjacent_to(&self, symbol: &Symbol, adjacency: Adjacency) -> Vec<&Number> {
+        let mut indices = adjacency.around(symbol.coords)
+            .filter_map(|cell| self.number_at.get(&cell).copied())
+            .collect::<Vec<_>>();
+        indices.sort();
+        indices.dedup();
+
+        indices.into_iter().map(|index| &self.numbers[index]).collect()
+    }
+
+    pub fn part_numbers(&self, adjacency: Adjacency) -> impl Iterator<Item=&Number> {
+        self.numbers.iter()
+            .filter(move |number| self.symbols_adjacent_to(number, adjacency).next().is_some())
+    }
+
+    /// Returns the symbols that qualify as gears under `rule`, with their ratios.
+    /// Gears whose ratio overflows are left out
+    pub fn gears(&self, rule: &GearRule) -> Vec<(&Symbol, u64)> {
+        self.symbols.iter()
+            .filter(|symbol| rule.symbols.contains(&symbol.ch))
+            .filter_map(|symbol| {
+                let values = self.numbers_adjacent_to(symbol, rule.adjacency)
+                    .into_iter()
+                    .map(|number| number.value)
+                    .collect::<Vec<_>>();
+                if rule.neighbours.contains(&values.len()) {
+                    rule.combine.apply(&values).map(|ratio| (symbol, ratio))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+impl FromIterator<SchematicObject> for Schematic {
+    fn from_iter<T: IntoIterator<Item = SchematicObject>>(iter: T) -> Self {
+        let mut schematic = Schematic::new();
+        for object in iter {
+            schematic.add(object);
+        }
+        schematic
+    }
+}
+
 pub fn parse_line(line: &str, row: i32) -> Vec<SchematicObject> {
     let mut result = vec![];
     let mut members = vec![];
@@ -137,7 +336,8 @@ pub fn parse_line(line: &str, row: i32) -> Vec<SchematicObject> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{parse_line, Number, SchematicObject, Symbol};
+    use crate::graph::Graph;
+    use crate::{parse_line, Adjacency, Combine, GearRule, Number, Schematic, SchematicObject, Symbol};
 
     const SAMPLE_INPUT: &str = include_str!("../input.small");
 
@@ -245,4 +445,156 @@ mod tests {
 
         assert_eq!(gears, test_gears);
     }
+
+    fn build_schematic(source: &str) -> Schematic {
+        source.lines()
+            .enumerate()
+            .flat_map(|(row, line)| parse_line(line, row as i32))
+            .collect()
+    }
+
+    #[test]
+    fn schematic_agrees_with_scan() {
+        let (symbols, numbers) = collect_objects(SAMPLE_INPUT);
+        let schematic = build_schematic(SAMPLE_INPUT);
+
+        assert_eq!(schematic.numbers(), &numbers[..]);
+        assert_eq!(schematic.symbols(), &symbols[..]);
+
+        let parts = schematic.part_numbers(Adjacency::Moore).collect::<Vec<_>>();
+        let scanned_parts = numbers.iter()
+            .filter(|num| num.is_part_num(&symbols))
+            .collect::<Vec<_>>();
+        assert_eq!(parts, scanned_parts);
+        assert_eq!(parts.iter().map(|part| part.value).sum::<u32>(), 4361);
+
+        let gears = schematic.gears(&GearRule::default());
+        assert_eq!(gears, vec![
+            (&Symbol::new('*', 3, 1), 16345),
+            (&Symbol::new('*', 5, 8), 451490),
+        ]);
+    }
+
+    #[test]
+    fn schematic_adjacency() {
+        let schematic = build_schematic(SAMPLE_INPUT);
+
+        let star = Symbol::new('*', 3, 4);
+        assert_eq!(
+            schematic.numbers_adjacent_to(&star, Adjacency::Moore),
+            vec![&Number::new(617, &[0, 1, 2], 4)]);
+
+        let number = Number::new(633, &[6, 7, 8], 2);
+        assert_eq!(
+            schematic.symbols_adjacent_to(&number, Adjacency::Moore).collect::<Vec<_>>(),
+            vec![&Symbol::new('#', 6, 3)]);
+
+        let number = Number::new(114, &[5, 6, 7], 0);
+        assert_eq!(schematic.symbols_adjacent_to(&number, Adjacency::Moore).count(), 0);
+    }
+
+    #[test]
+    fn adjacency_kernels() {
+        let schematic = build_schematic(SAMPLE_INPUT);
+
+        // 467 only touches the '*' below it diagonally
+        let number = Number::new(467, &[0, 1, 2], 0);
+        assert_eq!(schematic.symbols_adjacent_to(&number, Adjacency::Orthogonal).count(), 0);
+        assert_eq!(schematic.symbols_adjacent_to(&number, Adjacency::Radius(1)).count(), 1);
+
+        // 114 is two columns away from the '*' at (3, 1)
+        let number = Number::new(114, &[5, 6, 7], 0);
+        assert_eq!(
+            schematic.symbols_adjacent_to(&number, Adjacency::Radius(2)).collect::<Vec<_>>(),
+            vec![&Symbol::new('*', 3, 1)]);
+
+        let parts = schematic.part_numbers(Adjacency::Orthogonal)
+            .map(|part| part.value)
+            .collect::<Vec<_>>();
+        assert_eq!(parts, vec![35, 633, 617, 664, 598]);
+    }
+
+    #[test]
+    fn custom_gear_rules() {
+        let schematic = build_schematic(SAMPLE_INPUT);
+
+        let rule = GearRule {
+            neighbours: 1..=2,
+            combine: Combine::Sum,
+            ..GearRule::default()
+        };
+        let ratios = schematic.gears(&rule)
+            .into_iter()
+            .map(|(_, ratio)| ratio)
+            .collect::<Vec<_>>();
+        assert_eq!(ratios, vec![502, 617, 1353]);
+
+        let rule = GearRule {
+            symbols: "#+$".chars().collect(),
+            neighbours: 1..=1,
+            combine: Combine::Custom(Box::new(|values| values[0] as u64 + 1)),
+            ..GearRule::default()
+        };
+        let gears = schematic.gears(&rule);
+        assert_eq!(gears, vec![
+            (&Symbol::new('#', 6, 3), 634),
+            (&Symbol::new('+', 5, 5), 593),
+            (&Symbol::new('$', 3, 8), 665),
+        ]);
+    }
+
+    #[test]
+    fn overflowing_ratios() {
+        let schematic = build_schematic("9999.9999\n9999*9999\n9999.9999\n.........\n..12*34..");
+
+        let rule = GearRule {
+            neighbours: 2..=8,
+            ..GearRule::default()
+        };
+        assert_eq!(schematic.gears(&rule), vec![(&Symbol::new('*', 4, 4), 408)]);
+
+        let rule = GearRule {
+            neighbours: 2..=8,
+            combine: Combine::Sum,
+            ..GearRule::default()
+        };
+        assert_eq!(schematic.gears(&rule), vec![
+            (&Symbol::new('*', 4, 1), 59994),
+            (&Symbol::new('*', 4, 4), 46),
+        ]);
+    }
+
+    #[test]
+    fn connectivity_graph() {
+        let schematic = build_schematic(SAMPLE_INPUT);
+        let graph = Graph::new(&schematic, Adjacency::Moore);
+
+        assert_eq!(graph.edges().count(), 8);
+        assert_eq!(
+            graph.isolated_numbers().iter().map(|num| num.value).collect::<Vec<_>>(),
+            vec![114, 58]);
+
+        let components = graph.components();
+        assert_eq!(components.len(), 8);
+        assert_eq!(components[0].numbers.iter().map(|num| num.value).collect::<Vec<_>>(), vec![467, 35]);
+        assert_eq!(components[0].symbols, vec![&Symbol::new('*', 3, 1)]);
+        assert_eq!(components[1].numbers.iter().map(|num| num.value).collect::<Vec<_>>(), vec![114]);
+        assert!(components[1].symbols.is_empty());
+    }
+
+    #[test]
+    fn graph_export() {
+        let schematic = build_schematic("12*.\n...\"");
+        let graph = Graph::new(&schematic, Adjacency::Moore);
+
+        assert_eq!(
+            graph.to_dot(),
+            "graph {\n\tn0 [label=\"12\"];\n\ts0 [label=\"*\", shape=box];\n\ts1 [label=\"\\\"\", shape=box];\n\tn0 -- s0;\n}\n");
+        assert_eq!(
+            graph.to_json(),
+            concat!(
+                "{\"numbers\":[{\"id\":0,\"value\":12,\"row\":0,\"left_col\":0,\"right_col\":1}],",
+                "\"symbols\":[{\"id\":0,\"symbol\":\"*\",\"row\":0,\"col\":2},{\"id\":1,\"symbol\":\"\\\"\",\"row\":1,\"col\":3}],",
+                "\"edges\":[{\"number\":0,\"symbol\":0}]}"));
+    }
 }
@@ -1,11 +1,41 @@
+use std::env;
 use std::io::stdin;
+use std::process::exit;
+
+use num::BigUint;
 
 use day04::Card;
+use day04::scoring::{Rule, Scoring};
 
 fn main() {
+    let mut rule = Rule::Doubling;
+    let mut saturating = false;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--saturating" => saturating = true,
+            name => match name.parse() {
+                Ok(parsed) => rule = parsed,
+                Err(err) => {
+                    eprintln!("{err}. Use one of 'doubling', 'linear' or 'fibonacci'");
+                    exit(1);
+                }
+            },
+        }
+    }
+
     let cards = stdin()
         .lines()
-        .map(|st| Card::parse(&st.unwrap()).value());
+        .map(|st| Card::parse(&st.unwrap()));
 
-    println!("The total value of the cards is {}", cards.sum::<u64>());
+    if saturating {
+        let total = cards
+            .map(|card| rule.score_saturating(card.winner_count()))
+            .fold(0u64, |acc, value| acc.saturating_add(value));
+        println!("The total value of the cards is {}", total);
+    } else {
+        let total = cards
+            .map(|card| card.score(&rule))
+            .sum::<BigUint>();
+        println!("The total value of the cards is {}", total);
+    }
 }
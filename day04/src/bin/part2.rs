@@ -1,29 +1,18 @@
 use std::io::stdin;
-use day04::Card;
 
-fn main() {
+use anyhow::{Context, Result};
+
+use day04::{Card, ScratchcardPile};
+
+fn main() -> Result<()> {
     let cards = stdin()
         .lines()
-        .map(|st| Card::parse(&st.unwrap()));
-
-    let mut copies = vec![1usize];
+        .map(|st| Card::parse(&st.unwrap()))
+        .collect();
 
-    for (idx, card) in cards.enumerate() {
-        if copies.len() == idx {
-            copies.push(1);
-        }
-        let multiplier = copies[idx];
-        let winners = card.winner_count();
-        let left_to_count = copies.len() - (idx + 1);
-        let missing = winners.saturating_sub(left_to_count);
-        if missing > 0 {
-            let mut new = vec![1usize; missing];
-            copies.append(&mut new);
-        }
-        copies[idx+1..=idx+winners]
-            .iter_mut()
-            .for_each(|elem| *elem += multiplier);
-    }
+    let pile = ScratchcardPile::new(cards).context("Too many copies to count")?;
+    let total = pile.total().context("The total number of scratchcards overflows")?;
+    println!("The total scratchcards: {}", total);
 
-    println!("The total scratchcards: {}", copies.iter().sum::<usize>());
+    Ok(())
 }
@@ -1,5 +1,11 @@
+pub mod scoring;
+
 use std::collections::HashSet;
 
+use num::BigUint;
+
+use scoring::{Doubling, Scoring};
+
 #[derive(Debug, PartialEq)]
 pub struct Card {
     id: usize,
@@ -26,6 +32,10 @@ impl Card {
         }
     }
 
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
     pub fn winner_count(&self) -> usize {
         self.owned
             .iter()
@@ -33,20 +43,69 @@ impl Card {
             .count()
     }
 
+    /// Value of the card under the puzzle rules, saturating at u64::MAX
     pub fn value(&self) -> u64 {
-        let matches = self.winner_count();
-        if matches > 0 {
-            1u64 << (matches - 1)
-        } else {
-            0
+        Doubling.score_saturating(self.winner_count())
+    }
+
+    pub fn score<S: Scoring + ?Sized>(&self, scoring: &S) -> BigUint {
+        scoring.score(self.winner_count())
+    }
+}
+
+/// A pile of scratchcards, with the number of copies of each card that we end
+/// up holding once every card has been scratched
+#[derive(Debug)]
+pub struct ScratchcardPile {
+    cards: Vec<Card>,
+    copies: Vec<u128>,
+}
+
+impl ScratchcardPile {
+    /// Runs the copy cascade over the cards, in the order given. Winners that
+    /// would copy cards past the end of the pile are ignored. Returns None
+    /// if the number of copies of any card overflows.
+    pub fn new(cards: Vec<Card>) -> Option<Self> {
+        let mut copies = vec![1u128; cards.len()];
+
+        for (idx, card) in cards.iter().enumerate() {
+            let multiplier = copies[idx];
+            let last = std::cmp::min(idx + card.winner_count(), cards.len() - 1);
+            for elem in copies[idx+1..=last].iter_mut() {
+                *elem = elem.checked_add(multiplier)?;
+            }
         }
+
+        Some(ScratchcardPile { cards, copies })
+    }
+
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
+
+    /// Iterates over the card IDs and the final number of copies of each card
+    pub fn copies(&self) -> impl Iterator<Item=(usize, u128)> + '_ {
+        self.cards.iter()
+            .zip(self.copies.iter())
+            .map(|(card, &count)| (card.id, count))
+    }
+
+    pub fn copies_of(&self, id: usize) -> Option<u128> {
+        self.copies().find(|&(card_id, _)| card_id == id).map(|(_, count)| count)
+    }
+
+    /// Total number of scratchcards, or None if it doesn't fit in a u128
+    pub fn total(&self) -> Option<u128> {
+        self.copies.iter().try_fold(0u128, |acc, &count| acc.checked_add(count))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
-    use crate::Card;
+    use num::BigUint;
+    use crate::{Card, ScratchcardPile};
+    use crate::scoring::{Doubling, Fibonacci, Linear, Rule, Scoring};
 
     const SAMPLE_INPUT: &str = include_str!("../input.small");
 
@@ -71,4 +130,67 @@ mod tests {
 
         assert_eq!(cards, [8, 2, 2, 1, 0, 0])
     }
+
+    #[test]
+    fn copy_cascade() {
+        let cards = SAMPLE_INPUT.lines().map(Card::parse).collect();
+        let pile = ScratchcardPile::new(cards).unwrap();
+
+        assert_eq!(
+            pile.copies().collect::<Vec<_>>(),
+            vec![(1, 1), (2, 2), (3, 4), (4, 8), (5, 14), (6, 1)]);
+        assert_eq!(pile.copies_of(4), Some(8));
+        assert_eq!(pile.copies_of(7), None);
+        assert_eq!(pile.total(), Some(30));
+    }
+
+    #[test]
+    fn cascade_clamped_to_pile() {
+        // The first three cards win copies of cards beyond the end of the pile
+        let cards = SAMPLE_INPUT.lines().take(3).map(Card::parse).collect();
+        let pile = ScratchcardPile::new(cards).unwrap();
+
+        assert_eq!(
+            pile.copies().collect::<Vec<_>>(),
+            vec![(1, 1), (2, 2), (3, 4)]);
+        assert_eq!(pile.total(), Some(7));
+
+        assert_eq!(ScratchcardPile::new(vec![]).unwrap().total(), Some(0));
+    }
+
+    #[test]
+    fn cascade_overflow() {
+        // Every card wins copies of the next 32 ones, so the count nearly
+        // doubles from one card to the next
+        let card = "Card 1: 1 | 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1";
+        let cards = (0..130).map(|_| Card::parse(card)).collect::<Vec<_>>();
+
+        assert!(ScratchcardPile::new(cards).is_none());
+    }
+
+    #[test]
+    fn scoring_rules() {
+        let cards = SAMPLE_INPUT.lines().map(Card::parse).collect::<Vec<_>>();
+        let scores = |scoring: &dyn Scoring| cards.iter()
+            .map(|card| card.score(scoring))
+            .collect::<Vec<_>>();
+        let expected = |values: [u32; 6]| values.map(BigUint::from).to_vec();
+
+        assert_eq!(scores(&Doubling), expected([8, 2, 2, 1, 0, 0]));
+        assert_eq!(scores(&Linear), expected([4, 2, 2, 1, 0, 0]));
+        assert_eq!(scores(&Fibonacci), expected([5, 2, 2, 1, 0, 0]));
+        assert_eq!(scores(&|matches: usize| BigUint::from(matches * 10)), expected([40, 20, 20, 10, 0, 0]));
+
+        assert_eq!("fibonacci".parse::<Rule>(), Ok(Rule::Fibonacci));
+        assert!("tripling".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn large_scores() {
+        assert_eq!(Doubling.score(100), BigUint::from(1u32) << 99);
+        assert_eq!(Doubling.score_saturating(64), 1 << 63);
+        assert_eq!(Doubling.score_saturating(65), u64::MAX);
+        assert_eq!(Fibonacci.score_saturating(100), u64::MAX);
+        assert_eq!(Fibonacci.score(10), BigUint::from(89u32));
+    }
 }
@@ -0,0 +1,325 @@
+use std::{collections::HashMap, io::BufRead, ops::Range};
+
+use anyhow::{bail, Result};
+
+use crate::{Map, Mapping};
+
+// Indices of the maps leaving each category
+type CategoryIndex = HashMap<String, Vec<usize>>;
+
+/// One step in the lineage of a seed (or a range of them): where it ended up
+/// in a category, and the mapping that took it there
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step<'a, T> {
+    pub category: &'a str,
+    pub value: T,
+    pub mapping: Option<&'a Mapping>,
+}
+
+/// A collection of maps, indexed by category name. The categories must form
+/// a path or, more generally, a DAG. Values can be converted between any two
+/// categories joined by a single route, and the longest route (seed to
+/// location, for the puzzle) is taken as the main chain.
+#[derive(Debug, Clone)]
+pub struct Almanac {
+    maps: Vec<Map>,
+    by_source: CategoryIndex,
+    // Categories in topological order
+    order: Vec<String>,
+    // Indices of the maps in the main chain
+    chain: Vec<usize>,
+}
+
+impl Almanac {
+    /// Builds the almanac, taking the longest route as the main chain. Fails
+    /// if the maps form a cycle, if two maps join the same categories, or
+    /// if there are several longest routes.
+    pub fn new(maps: Vec<Map>) -> Result<Self> {
+        let (by_source, order) = Almanac::index(&maps)?;
+        let mut almanac = Almanac { maps, by_source, order, chain: vec![] };
+        almanac.chain = almanac.longest_chain()?;
+
+        Ok(almanac)
+    }
+
+    /// Like `new`, but the main chain is the route between the given categories
+    pub fn with_chain(maps: Vec<Map>, from: &str, to: &str) -> Result<Self> {
+        let (by_source, order) = Almanac::index(&maps)?;
+        let mut almanac = Almanac { maps, by_source, order, chain: vec![] };
+        almanac.chain = almanac.route_indices(from, to)?;
+
+        Ok(almanac)
+    }
+
+    /// Reads maps from the stream until it is exhausted. They don't need to
+    /// be in any particular order
+    pub fn read<R: BufRead>(stream: &mut R) -> Result<Self> {
+        let mut maps = vec![];
+
+        while let Some(map) = Map::read(stream)? {
+            maps.push(map);
+        }
+
+        Almanac::new(maps)
+    }
+
+    fn index(maps: &[Map]) -> Result<(CategoryIndex, Vec<String>)> {
+        let mut by_source = CategoryIndex::new();
+        let mut incoming: HashMap<&str, usize> = HashMap::new();
+
+        for (idx, map) in maps.iter().enumerate() {
+            let targets = by_source.entry(map.source.clone()).or_default();
+            if targets.iter().any(|&other| maps[other].destination == map.destination) {
+                bail!("Duplicate map {}", map.transition());
+            }
+            targets.push(idx);
+            incoming.entry(&map.source).or_insert(0);
+            *incoming.entry(&map.destination).or_insert(0) += 1;
+        }
+
+        // Kahn's algorithm. Anything left out of the order is part of a cycle
+        let mut ready = incoming.iter()
+            .filter(|(_, &count)| count == 0)
+            .map(|(&category, _)| category)
+            .collect::<Vec<_>>();
+        ready.sort_by(|a, b| b.cmp(a));
+        let mut order = vec![];
+        while let Some(category) = ready.pop() {
+            order.push(category.to_string());
+            for &idx in by_source.get(category).into_iter().flatten() {
+                let destination = maps[idx].destination.as_str();
+                let count = incoming.get_mut(destination).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(destination);
+                }
+            }
+        }
+
+        if order.len() < incoming.len() {
+            let mut stuck = incoming.into_iter()
+                .filter(|(_, count)| *count > 0)
+                .map(|(category, _)| category)
+                .collect::<Vec<_>>();
+            stuck.sort();
+            bail!("The maps form a cycle involving: {}", stuck.join(", "));
+        }
+
+        Ok((by_source, order))
+    }
+
+    fn outgoing(&self, category: &str) -> impl Iterator<Item=usize> + '_ {
+        self.by_source.get(category).into_iter().flatten().copied()
+    }
+
+    fn longest_chain(&self) -> Result<Vec<usize>> {
+        // For each category: length of the longest route ending there, how
+        // many routes have that length, and the last map in one of them
+        let mut best: HashMap<&str, (usize, usize, Option<usize>)> = self.order.iter()
+            .map(|category| (category.as_str(), (0, 1, None)))
+            .collect();
+
+        for category in self.order.iter() {
+            let (length, count, _) = best[category.as_str()];
+            for idx in self.outgoing(category) {
+                let entry = best.get_mut(self.maps[idx].destination.as_str()).unwrap();
+                if length + 1 > entry.0 {
+                    *entry = (length + 1, count, Some(idx));
+                } else if length + 1 == entry.0 {
+                    entry.1 += count;
+                }
+            }
+        }
+
+        let longest = best.values().map(|(length, _, _)| *length).max().unwrap_or(0);
+        let ends = best.iter()
+            .filter(|(_, (length, _, _))| *length == longest)
+            .collect::<Vec<_>>();
+        if longest > 0 && ends.iter().map(|(_, (_, count, _))| count).sum::<usize>() > 1 {
+            bail!("There are several longest routes. Choose one with Almanac::with_chain");
+        }
+
+        let mut chain = vec![];
+        let mut last = ends.first().and_then(|(_, (_, _, last))| *last);
+        while let Some(idx) = last {
+            chain.push(idx);
+            last = best[self.maps[idx].source.as_str()].2;
+        }
+        chain.reverse();
+
+        Ok(chain)
+    }
+
+    fn route_indices(&self, from: &str, to: &str) -> Result<Vec<usize>> {
+        for category in [from, to] {
+            if !self.order.iter().any(|known| known == category) {
+                bail!("Unknown category {category}");
+            }
+        }
+
+        // Number of routes from `from` to each category, and the last map
+        // in one of them
+        let mut routes: HashMap<&str, (usize, Option<usize>)> = HashMap::from([(from, (1, None))]);
+        for category in self.order.iter() {
+            let Some(&(count, _)) = routes.get(category.as_str()) else { continue };
+            for idx in self.outgoing(category) {
+                let entry = routes.entry(self.maps[idx].destination.as_str()).or_insert((0, Some(idx)));
+                entry.0 += count;
+            }
+        }
+
+        match routes.get(to) {
+            None => bail!("No route from {from} to {to}"),
+            Some((count, _)) if *count > 1 => bail!("There are several routes from {from} to {to}"),
+            _ => {}
+        }
+
+        let mut route = vec![];
+        let mut last = routes[to].1;
+        while let Some(idx) = last {
+            route.push(idx);
+            last = routes[self.maps[idx].source.as_str()].1;
+        }
+        route.reverse();
+
+        Ok(route)
+    }
+
+    pub fn maps(&self) -> &[Map] {
+        &self.maps
+    }
+
+    /// The maps in the main chain, in order
+    pub fn chain(&self) -> impl DoubleEndedIterator<Item=&Map> + '_ {
+        self.chain.iter().map(|&idx| &self.maps[idx])
+    }
+
+    /// The maps taking values from one category to another, in order
+    pub fn route(&self, from: &str, to: &str) -> Result<Vec<&Map>> {
+        Ok(self.route_indices(from, to)?
+           .into_iter()
+           .map(|idx| &self.maps[idx])
+           .collect())
+    }
+
+    /// Converts a value between any two categories joined by a single route
+    pub fn convert(&self, value: usize, from: &str, to: &str) -> Result<usize> {
+        Ok(self.route(from, to)?
+           .into_iter()
+           .fold(value, |acc, map| map.map_value(acc)))
+    }
+
+    /// Applies every map in the main chain, in turn, to the value
+    pub fn map_value(&self, value: usize) -> usize {
+        self.chain()
+            .fold(value, |acc, map| map.map_value(acc))
+    }
+
+    /// Collapses the main chain into a single map. None if there are no maps
+    pub fn flatten(&self) -> Option<Map> {
+        let mut chain = self.chain();
+        let first = chain.next()?;
+
+        Some(chain.fold(first.clone(), |acc, map| acc.compose(map)))
+    }
+
+    /// The chain going backwards, from the last category to the first. Only
+    /// exact if every map is a bijection
+    pub fn invert(&self) -> Almanac {
+        let maps = self.maps.iter().map(Map::invert).collect::<Vec<_>>();
+        let (by_source, order) = Almanac::index(&maps)
+            .expect("Reversing the maps can't introduce duplicates or cycles");
+
+        Almanac {
+            maps,
+            by_source,
+            order,
+            chain: self.chain.iter().rev().copied().collect(),
+        }
+    }
+
+    /// All the values in the first category that end up at `value`
+    pub fn preimage(&self, value: usize) -> Vec<usize> {
+        let mut result = self.chain()
+            .rev()
+            .fold(vec![value], |acc, map| acc.iter()
+                  .flat_map(|&value| map.preimage(value))
+                  .collect());
+        result.sort();
+        result.dedup();
+
+        result
+    }
+
+    /// All the values in the first category that end up within the ranges
+    pub fn preimage_ranges(&self, rngs: &[Range<usize>]) -> Vec<Range<usize>> {
+        self.chain()
+            .rev()
+            .fold(rngs.to_vec(), |acc, map| map.preimage_ranges(&acc))
+    }
+
+    /// Finds the lowest location reachable from any of the seed ranges, by
+    /// looking at every piece of the flattened chain whose seeds overlap the
+    /// ranges. The maps don't need to be injective, so the pieces can't be
+    /// taken in the order of their locations
+    pub fn lowest_location(&self, seeds: &[Range<usize>]) -> Option<usize> {
+        let flat = match self.flatten() {
+            Some(flat) => flat,
+            None => return seeds.iter().filter(|rng| !rng.is_empty()).map(|rng| rng.start).min(),
+        };
+
+        flat.segments(0..usize::MAX)
+            .into_iter()
+            .flat_map(|(source, dest, _)| seeds.iter()
+                      .filter(move |rng| rng.start < source.end && source.start < rng.end)
+                      .map(move |rng| dest.start + (std::cmp::max(rng.start, source.start) - source.start)))
+            .min()
+    }
+
+    /// Follows a value through the main chain, recording each step
+    pub fn trace(&self, value: usize) -> Vec<Step<'_, usize>> {
+        let Some(first) = self.chain().next() else { return vec![] };
+        let mut steps = vec![Step { category: first.source(), value, mapping: None }];
+
+        for map in self.chain() {
+            let value = steps.last().unwrap().value;
+            let mapping = map.find_mapping(value);
+            steps.push(Step { category: map.destination(), value: map.map_value(value), mapping });
+        }
+
+        steps
+    }
+
+    /// Follows a range of values through the main chain. The range is split
+    /// whenever its parts are handled by different mappings, so the result
+    /// has one lineage for each piece, with its steps narrowed down to the
+    /// values in that piece.
+    pub fn trace_range(&self, values: Range<usize>) -> Vec<Vec<Step<'_, Range<usize>>>> {
+        let Some(first) = self.chain().next() else { return vec![] };
+        let mut lineages = vec![vec![Step { category: first.source(), value: values, mapping: None }]];
+
+        for map in self.chain() {
+            let mut next = vec![];
+            for lineage in lineages {
+                let current = lineage.last().unwrap().value.clone();
+                for (source, dest, mapping) in map.segments(current.clone()) {
+                    // Every step shifts the values, so the piece sits at the
+                    // same offset in all of them
+                    let offset = source.start - current.start;
+                    let length = source.end - source.start;
+                    let mut narrowed = lineage.iter()
+                        .map(|step| Step {
+                            value: (step.value.start + offset)..(step.value.start + offset + length),
+                            ..step.clone()
+                        })
+                        .collect::<Vec<_>>();
+                    narrowed.push(Step { category: map.destination(), value: dest, mapping });
+                    next.push(narrowed);
+                }
+            }
+            lineages = next;
+        }
+
+        lineages
+    }
+}
@@ -1,18 +1,19 @@
 use std::io::{stdin, BufReader};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use day05::read_data;
+use day05::almanac::Almanac;
 
 fn main() -> Result<()>{
     let mut input = BufReader::new(stdin());
     let (seeds, maps) = read_data(&mut input)?;
+    let seed_to_location = Almanac::new(maps)?.flatten()
+        .ok_or(anyhow!("The almanac has no maps"))?;
 
     let smallest = seeds
         .iter()
-        .map(|&seed|
-             maps.iter()
-                .fold(seed, |acc, mapping| mapping.map_value(acc)))
-        .reduce(|acc, value| std::cmp::min(acc, value))
+        .map(|&seed| seed_to_location.map_value(seed))
+        .min()
         .unwrap();
     println!("Smallest: {smallest}");
 
@@ -1,20 +1,17 @@
 use std::io::{stdin, BufReader};
 
-use anyhow::Result;
-use day05::{read_seed_ranges, Map, sort_and_merge};
+use anyhow::{anyhow, Result};
+use day05::{read_seed_ranges, sort_and_merge};
+use day05::almanac::Almanac;
 
 fn main() -> Result<()>{
     let mut input = BufReader::new(stdin());
     let seed_ranges = read_seed_ranges(&mut input)?;
-    let mut maps = vec![];
+    let seed_to_location = Almanac::read(&mut input)?
+        .flatten()
+        .ok_or(anyhow!("The almanac has no maps"))?;
 
-    while let Some(map) = Map::read(&mut input)? {
-        maps.push(map);
-    }
-
-    let locations = maps
-        .iter()
-        .fold(sort_and_merge(seed_ranges), |acc, m| m.map_ranges(acc));
+    let locations = seed_to_location.map_ranges(sort_and_merge(seed_ranges));
 
     eprintln!("The lowest location is: {}", locations[0].start);
 
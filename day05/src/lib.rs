@@ -1,3 +1,6 @@
+pub mod almanac;
+pub mod validate;
+
 use std::{cmp::PartialOrd, io::BufRead, ops::Range};
 
 use anyhow::Result;
@@ -29,11 +32,19 @@ impl MappingResult {
     }
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Debug, Clone)]
 pub struct Mapping {
     source: Range<usize>,
     dest: Range<usize>,
     length: usize,
+    // Input line the mapping was read from, if any
+    line: Option<usize>,
+}
+
+impl PartialEq for Mapping {
+    fn eq(&self, other: &Self) -> bool {
+        (&self.source, &self.dest, self.length) == (&other.source, &other.dest, other.length)
+    }
 }
 
 impl Ord for Mapping {
@@ -54,9 +65,22 @@ impl Mapping {
             source: source_start..(source_start + length),
             dest: dest_start..(dest_start + length),
             length,
+            line: None,
         }
     }
 
+    pub fn source(&self) -> &Range<usize> {
+        &self.source
+    }
+
+    pub fn dest(&self) -> &Range<usize> {
+        &self.dest
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
     fn map(&self, value: usize) -> Option<usize> {
         if self.source.contains(&value) {
             Some(self.dest.start + (value - self.source.start))
@@ -106,18 +130,24 @@ impl Mapping {
                     vec![(rng.start + (self.length - offset))..rng.end]
                     )
             }
-            IntersectionExt::Empty => unimplemented!() // Won't happen, anyway
+            IntersectionExt::Empty => { // Zero-length mapping or range. Nothing to transform
+                let original = if rng.is_empty() { vec![] } else { vec![rng.clone()] };
+                MappingResult::only_orig(original)
+            }
         }
     }
 }
 
 impl Eq for Mapping {}
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct Map {
     source: String,
     destination: String,
-    pub mappings: Vec<Mapping>,
+    mappings: Vec<Mapping>,
+    // reach[i] is the largest source end among mappings[..=i]. Used to find
+    // the first matching mapping with a binary search, even if they overlap
+    reach: Vec<usize>,
 }
 
 impl Map {
@@ -125,22 +155,51 @@ impl Map {
         let mut mappings = mappings;
         mappings.sort();
 
+        let reach = mappings.iter()
+            .scan(0, |furthest, mapping| {
+                *furthest = std::cmp::max(*furthest, mapping.source.end);
+                Some(*furthest)
+            })
+            .collect();
+
         Map {
             source: source.into(),
             destination: destination.into(),
             mappings,
+            reach,
         }
     }
 
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
     pub fn transition(&self) -> String {
         format!("{} -> {}", self.source, self.destination)
     }
 
+    /// Reads the next map from the stream. Mappings are tagged with their
+    /// line numbers, counting from the header of the map
     pub fn read<R: BufRead>(stream: &mut R) -> Result<Option<Self>> {
+        Map::read_from_line(stream, &mut 0)
+    }
+
+    /// Like `read`, but `line` holds the number of lines already consumed
+    /// from the input, and is updated as more are read
+    pub fn read_from_line<R: BufRead>(stream: &mut R, line: &mut usize) -> Result<Option<Self>> {
         let mut buf = String::from("");
 
         // Read and parse the map header
         stream.read_line(&mut buf)?;
+        *line += 1;
 
         if buf.len() == 0 {
             return Ok(None);
@@ -154,6 +213,7 @@ impl Map {
         loop {
             buf.clear();
             stream.read_line(&mut buf)?;
+            *line += 1;
             if buf.trim().len() == 0 {
                 break;
             }
@@ -163,20 +223,82 @@ impl Map {
                 .map(|num| num.parse::<usize>().unwrap())
                 .collect::<Vec<_>>();
 
-            mappings.push(Mapping::new(numbers[0], numbers[1], numbers[2]));
+            let mut mapping = Mapping::new(numbers[0], numbers[1], numbers[2]);
+            mapping.line = Some(*line);
+            mappings.push(mapping);
         }
 
         Ok(Some(Map::new(&source, &destination, mappings)))
     }
 
     pub fn map_value(&self, value: usize) -> usize {
+        self.find_mapping(value)
+            .and_then(|mapping| mapping.map(value))
+            .unwrap_or(value)
+    }
+
+    /// The mapping that applies to the value, if any
+    pub fn find_mapping(&self, value: usize) -> Option<&Mapping> {
+        // The first mapping reaching past the value is the only candidate
+        let idx = self.reach.partition_point(|&end| end <= value);
+
+        self.mappings.get(idx)
+            .filter(|mapping| mapping.source.contains(&value))
+    }
+
+    /// Splits `range` into the pieces handled by each mapping, or by none
+    /// (in which case values map to themselves). Returns the source and
+    /// destination of each piece, in order, along with the mapping applied.
+    /// When mappings overlap, the first one wins, like in `map_value`.
+    pub fn segments(&self, range: Range<usize>) -> Vec<(Range<usize>, Range<usize>, Option<&Mapping>)> {
+        let mut result = vec![];
+        let mut cursor = range.start;
+
         for mapping in self.mappings.iter() {
-            if let Some(new_value) = mapping.map(value) {
-                return new_value
+            let start = std::cmp::max(mapping.source.start, cursor);
+            let end = std::cmp::min(mapping.source.end, range.end);
+            if start >= end {
+                continue;
             }
+            if cursor < start {
+                result.push((cursor..start, cursor..start, None));
+            }
+            let dest_start = mapping.dest.start + (start - mapping.source.start);
+            result.push((start..end, dest_start..(dest_start + end - start), Some(mapping)));
+            cursor = end;
+        }
+
+        if cursor < range.end {
+            result.push((cursor..range.end, cursor..range.end, None));
         }
 
-        value
+        result
+    }
+
+    /// Returns a single map equivalent to applying this map first, and then
+    /// `other`. Its mappings don't overlap, and adjacent ones with the same
+    /// offset are merged.
+    pub fn compose(&self, other: &Map) -> Map {
+        let mut mappings: Vec<Mapping> = vec![];
+
+        for (source, middle, _) in self.segments(0..usize::MAX) {
+            for (mid_piece, dest, _) in other.segments(middle.clone()) {
+                let start = source.start + (mid_piece.start - middle.start);
+                let length = mid_piece.end - mid_piece.start;
+                if start == dest.start {
+                    continue;
+                }
+
+                match mappings.last_mut() {
+                    Some(last) if last.source.end == start && last.dest.end == dest.start => {
+                        *last = Mapping::new(last.dest.start, last.source.start, last.length + length);
+                    }
+                    _ => mappings.push(Mapping::new(dest.start, start, length)),
+                }
+            }
+        }
+
+        Map::new(&self.source, &other.destination, mappings)
     }
 
     pub fn map_ranges(&self, rngs: Vec<Range<usize>>) -> Vec<Range<usize>> {
@@ -199,6 +321,51 @@ impl Map {
 
         sort_and_merge(vec![result.original, result.transformed].into_iter().flatten().collect())
     }
+
+    /// Returns the map going the other way around. This is an exact inverse
+    /// only if the map is a bijection (as in the puzzle inputs). Otherwise,
+    /// values with several preimages map to only one of them; use `preimage`
+    /// to get them all.
+    pub fn invert(&self) -> Map {
+        let mappings = self.segments(0..usize::MAX)
+            .into_iter()
+            .filter(|(source, dest, _)| source.start != dest.start)
+            .map(|(source, dest, _)| Mapping::new(source.start, dest.start, source.end - source.start))
+            .collect();
+
+        Map::new(&self.destination, &self.source, mappings)
+    }
+
+    /// All the values that this map takes to `value`, in ascending order
+    pub fn preimage(&self, value: usize) -> Vec<usize> {
+        let mut result = self.segments(0..usize::MAX)
+            .into_iter()
+            .filter(|(_, dest, _)| dest.contains(&value))
+            .map(|(source, dest, _)| source.start + (value - dest.start))
+            .collect::<Vec<_>>();
+        result.sort();
+
+        result
+    }
+
+    /// All the values that this map takes into any of the ranges, as sorted
+    /// and merged ranges
+    pub fn preimage_ranges(&self, rngs: &[Range<usize>]) -> Vec<Range<usize>> {
+        let mut result = vec![];
+
+        for (source, dest, _) in self.segments(0..usize::MAX) {
+            for rng in rngs {
+                let start = std::cmp::max(dest.start, rng.start);
+                let end = std::cmp::min(dest.end, rng.end);
+                if start < end {
+                    let offset = start - dest.start;
+                    result.push((source.start + offset)..(source.start + offset + end - start));
+                }
+            }
+        }
+
+        sort_and_merge(result)
+    }
 }
 
 pub fn read_seed_numbers<R: BufRead>(stream: &mut R) -> Result<Vec<usize>> {
@@ -243,8 +410,10 @@ pub fn read_seed_ranges<R: BufRead>(stream: &mut R) -> Result<Vec<Range<usize>>>
 pub fn read_data<R: BufRead>(stream: &mut R) -> Result<(Vec<usize>, Vec<Map>)> {
     let seed_numbers = read_seed_numbers(stream)?;
     let mut maps = vec![];
+    // The seeds and the blank line after them
+    let mut line = 2;
 
-    while let Some(map) = Map::read(stream)? {
+    while let Some(map) = Map::read_from_line(stream, &mut line)? {
         maps.push(map);
     }
 
@@ -280,7 +449,10 @@ pub fn sort_and_merge(ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{read_seed_numbers, Map, Mapping};
+    use crate::{read_data, read_seed_numbers, read_seed_ranges, Map, Mapping};
+    use crate::almanac::Almanac;
+    use crate::validate::Problem;
+    use crate::almanac::Step;
 
     const SMALL_INPUT: &str = include_str!("../input.small");
 
@@ -300,4 +472,256 @@ mod tests {
         let other = get_single_map();
         assert_eq!(map1, Some(other));
     }
+
+    #[test]
+    fn map_with_overlaps() {
+        // The first mapping, by source start, wins
+        let map = Map::new("a", "b", vec![
+            Mapping::new(100, 0, 10),
+            Mapping::new(200, 5, 15),
+            Mapping::new(300, 12, 3),
+        ]);
+
+        assert_eq!(map.map_value(7), 107);
+        assert_eq!(map.map_value(13), 208);
+        assert_eq!(map.map_value(20), 20);
+    }
+
+    #[test]
+    fn compose_maps() {
+        let first = Map::new("a", "b", vec![Mapping::new(10, 0, 10)]);
+        let second = Map::new("b", "c", vec![Mapping::new(0, 15, 10)]);
+        let composed = first.compose(&second);
+
+        assert_eq!(composed.transition(), "a -> c");
+        assert_eq!(composed.mappings(), &[
+            Mapping::new(10, 0, 5),
+            Mapping::new(0, 5, 5),
+            Mapping::new(0, 15, 10),
+        ]);
+        for value in 0..30 {
+            assert_eq!(composed.map_value(value), second.map_value(first.map_value(value)));
+        }
+
+        // Mapping values back to where they came from leaves no mapping for them
+        let back = Map::new("b", "a", vec![Mapping::new(0, 10, 10)]);
+        let composed = first.compose(&back);
+        assert!(composed.mappings().iter().all(|mapping| !mapping.source.contains(&3)));
+        assert_eq!(composed.map_value(3), 3);
+    }
+
+    // Small deterministic generator, to produce random-ish maps for the
+    // property tests below
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((self.0 >> 33) as usize) % bound
+        }
+    }
+
+    fn random_map(rng: &mut Lcg) -> Map {
+        let mappings = (0..rng.next(6))
+            .map(|_| Mapping::new(rng.next(100), rng.next(100), 1 + rng.next(30)))
+            .collect();
+
+        Map::new("a", "b", mappings)
+    }
+
+    #[test]
+    fn preimage_matches_forward_mapping() {
+        let mut rng = Lcg(2023);
+
+        for _ in 0..200 {
+            let map = random_map(&mut rng);
+
+            for value in 0..150 {
+                let mapped = map.map_value(value);
+                assert!(map.preimage(mapped).contains(&value));
+                assert!(map.preimage(value).iter().all(|&orig| map.map_value(orig) == value));
+            }
+
+            let start = rng.next(120);
+            let target = start..(start + 1 + rng.next(30));
+            let preimage = map.preimage_ranges(std::slice::from_ref(&target));
+            for value in 0..150 {
+                assert_eq!(
+                    preimage.iter().any(|rng| rng.contains(&value)),
+                    target.contains(&map.map_value(value)));
+            }
+        }
+    }
+
+    #[test]
+    fn invert_bijections() {
+        let mut rng = Lcg(1225);
+
+        for _ in 0..200 {
+            // Shuffle blocks of 10 values around, which always gives a bijection
+            let mut blocks = (0..10).collect::<Vec<_>>();
+            for idx in (1..blocks.len()).rev() {
+                blocks.swap(idx, rng.next(idx + 1));
+            }
+            let mappings = blocks.iter()
+                .enumerate()
+                .map(|(from, &to)| Mapping::new(to * 10, from * 10, 10))
+                .collect();
+            let map = Map::new("a", "b", mappings);
+            let inverse = map.invert();
+
+            assert_eq!(inverse.transition(), "b -> a");
+            for value in 0..120 {
+                assert_eq!(inverse.map_value(map.map_value(value)), value);
+                assert_eq!(map.preimage(value), vec![inverse.map_value(value)]);
+            }
+        }
+    }
+
+    #[test]
+    fn flatten_almanac() {
+        let (seeds, maps) = read_data(&mut SMALL_INPUT.as_bytes()).unwrap();
+        let almanac = Almanac::new(maps).unwrap();
+        let flat = almanac.flatten().unwrap();
+
+        assert_eq!(flat.transition(), "seed -> location");
+        assert_eq!(
+            seeds.iter().map(|&seed| flat.map_value(seed)).collect::<Vec<_>>(),
+            vec![82, 43, 86, 35]);
+        for value in 0..200 {
+            assert_eq!(flat.map_value(value), almanac.map_value(value));
+        }
+
+        let seed_ranges = read_seed_ranges(&mut SMALL_INPUT.as_bytes()).unwrap();
+        assert_eq!(flat.map_ranges(seed_ranges)[0].start, 46);
+    }
+
+    #[test]
+    fn reverse_queries() {
+        let (_, maps) = read_data(&mut SMALL_INPUT.as_bytes()).unwrap();
+        let almanac = Almanac::new(maps).unwrap();
+        let seed_ranges = read_seed_ranges(&mut SMALL_INPUT.as_bytes()).unwrap();
+
+        assert_eq!(almanac.preimage(35), vec![13]);
+        assert_eq!(almanac.lowest_location(&seed_ranges), Some(46));
+        assert_eq!(almanac.lowest_location(&[]), None);
+
+        // Two pieces landing on overlapping locations
+        let overlapping = Almanac::new(vec![
+            Map::new("seed", "location", vec![Mapping::new(0, 200, 100), Mapping::new(5, 500, 5)]),
+        ]).unwrap();
+        assert_eq!(overlapping.lowest_location(&[290..291, 500..501]), Some(5));
+
+        let inverse = almanac.invert();
+        for seed in 0..120 {
+            assert_eq!(inverse.map_value(almanac.map_value(seed)), seed);
+        }
+        let location = 46..47;
+        let seeds = almanac.preimage_ranges(std::slice::from_ref(&location));
+        assert!(seeds.iter().any(|rng| rng.contains(&82)));
+    }
+
+    #[test]
+    fn route_by_category() {
+        let (_, mut maps) = read_data(&mut SMALL_INPUT.as_bytes()).unwrap();
+        let almanac = Almanac::new(maps.clone()).unwrap();
+
+        assert_eq!(almanac.convert(79, "seed", "humidity").unwrap(), 78);
+        assert_eq!(almanac.convert(78, "humidity", "location").unwrap(), 82);
+        assert_eq!(almanac.convert(81, "soil", "soil").unwrap(), 81);
+        assert!(almanac.convert(82, "location", "seed").is_err());
+        assert!(almanac.convert(82, "seed", "moisture").is_err());
+
+        // Shuffling the maps makes no difference
+        maps.reverse();
+        maps.swap(1, 4);
+        let shuffled = Almanac::new(maps.clone()).unwrap();
+        assert_eq!(
+            shuffled.chain().map(|map| map.transition()).collect::<Vec<_>>(),
+            almanac.chain().map(|map| map.transition()).collect::<Vec<_>>());
+        assert_eq!(shuffled.map_value(79), 82);
+
+        // Neither does adding a branch
+        maps.push(Map::new("soil", "colour", vec![Mapping::new(0, 10, 10)]));
+        let branched = Almanac::new(maps.clone()).unwrap();
+        assert_eq!(branched.map_value(79), 82);
+        assert_eq!(branched.convert(15, "seed", "colour").unwrap(), 5);
+    }
+
+    #[test]
+    fn invalid_category_graphs() {
+        let map = |source: &str, destination: &str| Map::new(source, destination, vec![]);
+
+        let err = Almanac::new(vec![map("a", "b"), map("b", "c"), map("c", "a")]).unwrap_err();
+        assert_eq!(err.to_string(), "The maps form a cycle involving: a, b, c");
+
+        assert!(Almanac::new(vec![map("a", "b"), map("a", "b")]).is_err());
+
+        // Two routes from a to c: the main chain is ambiguous, unless chosen
+        let maps = vec![map("a", "b"), map("b", "c"), map("a", "d"), map("d", "c")];
+        assert!(Almanac::new(maps.clone()).is_err());
+        let almanac = Almanac::with_chain(maps.clone(), "a", "b").unwrap();
+        assert_eq!(almanac.chain().count(), 1);
+        assert!(almanac.convert(0, "a", "c").is_err());
+        assert!(Almanac::with_chain(maps, "a", "c").is_err());
+    }
+
+    #[test]
+    fn validate_maps() {
+        let (_, maps) = read_data(&mut SMALL_INPUT.as_bytes()).unwrap();
+        assert_eq!(maps[1].mappings()[0].line(), Some(10));
+        assert!(maps.iter().all(|map| map.validate().is_empty()));
+
+        let input = "a-to-b map:\n10 0 5\n0 3 4\n20 9 0\n12 20 5\n";
+        let map = Map::read(&mut input.as_bytes()).unwrap().unwrap();
+        assert_eq!(map.validate(), vec![
+            Problem::ZeroLength { line: Some(4) },
+            Problem::OverlappingSources { lines: (Some(2), Some(3)), overlap: 3..5 },
+            Problem::OverlappingDestinations { lines: (Some(2), Some(5)), overlap: 12..15 },
+            Problem::Gap { lines: (Some(3), Some(5)), gap: 7..20 },
+        ]);
+        assert_eq!(
+            map.validate()[1].to_string(),
+            "line 2 and line 3: source ranges overlap on 3..5");
+        assert!(map.validate().iter().take(3).all(Problem::is_error));
+        assert!(!map.validate()[3].is_error());
+
+        // Gaps name the lines in the order they appear
+        let input = "a-to-b map:\n0 20 5\n0 0 5\n";
+        let map = Map::read(&mut input.as_bytes()).unwrap().unwrap();
+        assert_eq!(map.validate(), vec![
+            Problem::OverlappingDestinations { lines: (Some(2), Some(3)), overlap: 0..5 },
+            Problem::Gap { lines: (Some(2), Some(3)), gap: 5..20 },
+        ]);
+
+        // Zero-length mappings are harmless when mapping ranges
+        assert_eq!(map.map_ranges(vec![9..10, 30..31]), vec![9..10, 30..31]);
+    }
+
+    #[test]
+    fn trace_lineage() {
+        let (_, maps) = read_data(&mut SMALL_INPUT.as_bytes()).unwrap();
+        let almanac = Almanac::new(maps).unwrap();
+
+        let lineage = almanac.trace(79);
+        assert_eq!(
+            lineage.iter().map(|step| (step.category, step.value)).collect::<Vec<_>>(),
+            vec![("seed", 79), ("soil", 81), ("fertilizer", 81), ("water", 81),
+                 ("light", 74), ("temperature", 78), ("humidity", 78), ("location", 82)]);
+        assert_eq!(lineage[1].mapping, Some(&Mapping::new(52, 50, 48)));
+        assert_eq!(lineage[1].mapping.unwrap().line(), Some(5));
+        assert_eq!(lineage[2].mapping, None);
+
+        let lineages = almanac.trace_range(79..93);
+        for lineage in lineages.iter() {
+            let Step { value: seeds, .. } = &lineage[0];
+            let Step { value: locations, .. } = lineage.last().unwrap();
+            assert_eq!(seeds.len(), locations.len());
+            for (seed, location) in seeds.clone().zip(locations.clone()) {
+                assert_eq!(almanac.map_value(seed), location);
+            }
+        }
+        assert_eq!(lineages.iter().map(|lineage| lineage[0].value.len()).sum::<usize>(), 14);
+        assert_eq!(lineages.iter().map(|lineage| lineage.last().unwrap().value.start).min(), Some(46));
+    }
 }
@@ -0,0 +1,192 @@
+use std::env;
+use std::io::{stdin, BufRead, BufReader};
+use std::process::exit;
+
+use anyhow::Result;
+use day05::{read_seed_numbers, read_seed_ranges, Map, Mapping};
+use day05::almanac::{Almanac, Step};
+
+// Reads all the maps, keeping track of the line numbers. Assumes that the
+// seeds and the blank line after them have been consumed already
+fn read_maps<R: BufRead>(input: &mut R) -> Result<Vec<Map>> {
+    let mut maps = vec![];
+    let mut line = 2;
+
+    while let Some(map) = Map::read_from_line(input, &mut line)? {
+        maps.push(map);
+    }
+
+    Ok(maps)
+}
+
+fn lint() -> Result<bool> {
+    let mut input = BufReader::new(stdin());
+    read_seed_numbers(&mut input)?;
+
+    let maps = read_maps(&mut input)?;
+    let mut clean = true;
+    for map in maps.iter() {
+        for problem in map.validate() {
+            if problem.is_error() {
+                println!("{}: {problem}", map.transition());
+                clean = false;
+            } else {
+                println!("{}: note: {problem}", map.transition());
+            }
+        }
+    }
+
+    if let Err(err) = Almanac::new(maps) {
+        println!("{err}");
+        clean = false;
+    }
+
+    Ok(clean)
+}
+
+// A step of a lineage, ready for output: the fields describing the value
+// (one for single values, start and end for ranges), and the mapping applied
+struct Cell<'a> {
+    fields: Vec<String>,
+    mapping: Option<&'a Mapping>,
+}
+
+impl<'a> From<&Step<'a, usize>> for Cell<'a> {
+    fn from(step: &Step<'a, usize>) -> Self {
+        Cell { fields: vec![step.value.to_string()], mapping: step.mapping }
+    }
+}
+
+impl<'a> From<&Step<'a, std::ops::Range<usize>>> for Cell<'a> {
+    fn from(step: &Step<'a, std::ops::Range<usize>>) -> Self {
+        Cell {
+            fields: vec![step.value.start.to_string(), step.value.end.to_string()],
+            mapping: step.mapping,
+        }
+    }
+}
+
+fn describe_mapping(mapping: Option<&Mapping>) -> String {
+    match mapping {
+        Some(mapping) => match mapping.line() {
+            Some(line) => format!(" (line {line})"),
+            None => " (mapped)".to_string(),
+        },
+        None => "".to_string(),
+    }
+}
+
+fn print_table(categories: &[&str], rows: &[Vec<Cell>]) {
+    let mut lines = vec![categories.iter().map(|cat| cat.to_string()).collect::<Vec<_>>()];
+    for row in rows {
+        lines.push(row.iter()
+                   .map(|cell| format!("{}{}", cell.fields.join(".."), describe_mapping(cell.mapping)))
+                   .collect());
+    }
+
+    let widths = (0..categories.len())
+        .map(|col| lines.iter().map(|line| line[col].len()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+    for line in lines {
+        let padded = line.iter()
+            .zip(widths.iter())
+            .map(|(text, &width)| format!("{text:<width$}"))
+            .collect::<Vec<_>>();
+        println!("{}", padded.join(" | ").trim_end());
+    }
+}
+
+fn print_csv(categories: &[&str], rows: &[Vec<Cell>], ranges: bool) {
+    let mut header = vec![];
+    for (idx, category) in categories.iter().enumerate() {
+        if ranges {
+            header.push(format!("{category}_start"));
+            header.push(format!("{category}_end"));
+        } else {
+            header.push(category.to_string());
+        }
+        if idx > 0 {
+            header.push(format!("{category}_line"));
+        }
+    }
+    println!("{}", header.join(","));
+
+    for row in rows {
+        let mut fields = vec![];
+        for (idx, cell) in row.iter().enumerate() {
+            fields.extend(cell.fields.iter().cloned());
+            if idx > 0 {
+                fields.push(cell.mapping.and_then(|mapping| mapping.line()).map(|line| line.to_string()).unwrap_or_default());
+            }
+        }
+        println!("{}", fields.join(","));
+    }
+}
+
+fn trace(ranges: bool, csv: bool) -> Result<()> {
+    let mut input = BufReader::new(stdin());
+    let seeds = if ranges {
+        read_seed_ranges(&mut input)?
+    } else {
+        read_seed_numbers(&mut input)?
+            .into_iter()
+            .map(|seed| seed..(seed + 1))
+            .collect()
+    };
+    let almanac = Almanac::new(read_maps(&mut input)?)?;
+
+    let mut categories = almanac.chain().map(|map| map.source()).take(1).collect::<Vec<_>>();
+    categories.extend(almanac.chain().map(|map| map.destination()));
+
+    let rows = if ranges {
+        seeds.into_iter()
+            .flat_map(|rng| almanac.trace_range(rng))
+            .map(|lineage| lineage.iter().map(Cell::from).collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+    } else {
+        seeds.into_iter()
+            .map(|rng| almanac.trace(rng.start).iter().map(Cell::from).collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+    };
+
+    if csv {
+        print_csv(&categories, &rows, ranges);
+    } else {
+        print_table(&categories, &rows);
+    }
+
+    Ok(())
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: day05 lint < input");
+    eprintln!("       day05 trace [--ranges] [--csv] < input");
+    exit(2);
+}
+
+fn main() -> Result<()> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    match args.first().map(String::as_str) {
+        Some("lint") => {
+            if !lint()? {
+                exit(1);
+            }
+        }
+        Some("trace") => {
+            let mut ranges = false;
+            let mut csv = false;
+            for arg in args[1..].iter() {
+                match arg.as_str() {
+                    "--ranges" => ranges = true,
+                    "--csv" => csv = true,
+                    _ => usage(),
+                }
+            }
+            trace(ranges, csv)?;
+        }
+        _ => usage(),
+    }
+
+    Ok(())
+}
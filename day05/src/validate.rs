@@ -0,0 +1,129 @@
+use std::fmt::{self, Display};
+use std::ops::Range;
+
+use crate::{Map, Mapping};
+
+/// Something suspicious about the mappings in a map
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+    /// The mapping has no values at all
+    ZeroLength { line: Option<usize> },
+    /// Values that two mappings claim. Only the first one applies to them
+    OverlappingSources { lines: (Option<usize>, Option<usize>), overlap: Range<usize> },
+    /// Values that two mappings produce, meaning the map is not injective
+    OverlappingDestinations { lines: (Option<usize>, Option<usize>), overlap: Range<usize> },
+    /// Values between two mappings that none of them covers. They map to
+    /// themselves, so this is only informational
+    Gap { lines: (Option<usize>, Option<usize>), gap: Range<usize> },
+}
+
+impl Problem {
+    /// Whether the problem makes the map suspicious, rather than just
+    /// worth knowing about
+    pub fn is_error(&self) -> bool {
+        !matches!(self, Problem::Gap { .. })
+    }
+}
+
+fn describe_line(line: Option<usize>) -> String {
+    match line {
+        Some(line) => format!("line {line}"),
+        None => "unknown line".to_string(),
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::ZeroLength { line } =>
+                write!(f, "{}: zero-length mapping", describe_line(*line)),
+            Problem::OverlappingSources { lines: (first, second), overlap } =>
+                write!(f, "{} and {}: source ranges overlap on {overlap:?}",
+                       describe_line(*first), describe_line(*second)),
+            Problem::OverlappingDestinations { lines: (first, second), overlap } =>
+                write!(f, "{} and {}: destination ranges overlap on {overlap:?}",
+                       describe_line(*first), describe_line(*second)),
+            Problem::Gap { lines: (first, second), gap } =>
+                write!(f, "{} and {}: values {gap:?} are not covered",
+                       describe_line(*first), describe_line(*second)),
+        }
+    }
+}
+
+fn overlap(a: &Range<usize>, b: &Range<usize>) -> Option<Range<usize>> {
+    let start = std::cmp::max(a.start, b.start);
+    let end = std::cmp::min(a.end, b.end);
+
+    if start < end { Some(start..end) } else { None }
+}
+
+fn overlaps<F>(mappings: &[&Mapping], range: F) -> Vec<(Option<usize>, Option<usize>, Range<usize>)>
+    where F: Fn(&Mapping) -> &Range<usize>
+{
+    let mut result = vec![];
+
+    for (idx, first) in mappings.iter().enumerate() {
+        for second in mappings[idx+1..].iter() {
+            if let Some(common) = overlap(range(first), range(second)) {
+                let (first, second) = if first.line <= second.line { (first, second) } else { (second, first) };
+                result.push((first.line, second.line, common));
+            }
+        }
+    }
+    result.sort_by_key(|(first, second, _)| (*first, *second));
+
+    result
+}
+
+impl Map {
+    /// Looks for zero-length mappings, overlapping sources or destinations,
+    /// and gaps between the source ranges. An empty result means the map is
+    /// a clean, injective function.
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = vec![];
+
+        let mut zero_length = self.mappings.iter()
+            .filter(|mapping| mapping.length == 0)
+            .map(|mapping| mapping.line)
+            .collect::<Vec<_>>();
+        zero_length.sort();
+        problems.extend(zero_length.into_iter().map(|line| Problem::ZeroLength { line }));
+
+        let non_empty = self.mappings.iter()
+            .filter(|mapping| mapping.length > 0)
+            .collect::<Vec<_>>();
+
+        problems.extend(
+            overlaps(&non_empty, |mapping| &mapping.source)
+                .into_iter()
+                .map(|(first, second, overlap)| Problem::OverlappingSources { lines: (first, second), overlap }));
+        problems.extend(
+            overlaps(&non_empty, |mapping| &mapping.dest)
+                .into_iter()
+                .map(|(first, second, overlap)| Problem::OverlappingDestinations { lines: (first, second), overlap }));
+
+        // Mappings are sorted by source, so gaps show up between consecutive ones
+        let mut furthest: Option<&Mapping> = None;
+        for mapping in non_empty {
+            if let Some(previous) = furthest {
+                if previous.source.end < mapping.source.start {
+                    let lines = if previous.line <= mapping.line {
+                        (previous.line, mapping.line)
+                    } else {
+                        (mapping.line, previous.line)
+                    };
+                    problems.push(Problem::Gap {
+                        lines,
+                        gap: previous.source.end..mapping.source.start,
+                    });
+                }
+                if previous.source.end >= mapping.source.end {
+                    continue;
+                }
+            }
+            furthest = Some(mapping);
+        }
+
+        problems
+    }
+}
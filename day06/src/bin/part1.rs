@@ -6,14 +6,11 @@ fn main() {
     let mut input = stdin().lines();
     let time_line = input.next().unwrap().unwrap();
     let distance_line = input.next().unwrap().unwrap();
-    let races = parse_problem(&time_line, &distance_line);
+    let races = parse_problem(&time_line, &distance_line).unwrap();
 
     let n_solutions = races
         .iter()
-        .map(|r| {
-            let sol = r.solve();
-            (sol.1 - sol.0) + 1
-        });
+        .map(|r| r.ways_to_win());
 
     let answer = n_solutions.reduce(|acc, n| acc * n).unwrap();
 
@@ -6,9 +6,7 @@ fn main() {
     let mut input = stdin().lines();
     let time_line = input.next().unwrap().unwrap();
     let distance_line = input.next().unwrap().unwrap();
-    let race = parse_kerning(&time_line, &distance_line);
+    let race = parse_kerning(&time_line, &distance_line).unwrap();
 
-    let (min, max) = race.solve();
-
-    eprintln!("Solutions: {:#?}", max - min + 1);
+    eprintln!("Solutions: {:#?}", race.ways_to_win());
 }
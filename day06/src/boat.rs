@@ -0,0 +1,149 @@
+/// How far a boat goes, given how long the button was held. The distance
+/// functions are expected to grow up to some point and then shrink, possibly
+/// with a flat top, which is what `winning_interval` relies on. Distances
+/// saturate at u128::MAX.
+pub trait BoatModel {
+    fn distance(&self, duration: u128, time_pressed: u128) -> u128;
+}
+
+/// The puzzle rules: every ms pressed adds 1 mm/ms of speed
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Linear;
+
+impl BoatModel for Linear {
+    fn distance(&self, duration: u128, time_pressed: u128) -> u128 {
+        duration.saturating_sub(time_pressed).saturating_mul(time_pressed)
+    }
+}
+
+/// Like `Linear`, but the speed can't go past `max_speed`
+#[derive(Debug, Clone, Copy)]
+pub struct Capped {
+    pub max_speed: u128,
+}
+
+impl BoatModel for Capped {
+    fn distance(&self, duration: u128, time_pressed: u128) -> u128 {
+        let speed = std::cmp::min(time_pressed, self.max_speed);
+
+        duration.saturating_sub(time_pressed).saturating_mul(speed)
+    }
+}
+
+/// Like `Linear`, but the boat loses `deceleration` mm/ms of speed for each
+/// ms it travels, until it stops
+#[derive(Debug, Clone, Copy)]
+pub struct Drag {
+    pub deceleration: u128,
+}
+
+impl BoatModel for Drag {
+    fn distance(&self, duration: u128, time_pressed: u128) -> u128 {
+        let travelling = duration.saturating_sub(time_pressed);
+        let speed = time_pressed;
+        // Number of ms during which the boat is still moving
+        let moving = match speed.checked_div(self.deceleration) {
+            Some(steps) => std::cmp::min(travelling, steps.saturating_add(1)),
+            None => travelling,
+        };
+        if moving == 0 {
+            return 0
+        }
+
+        // speed + (speed - d) + (speed - 2d) + ... for `moving` terms
+        let lost = if moving % 2 == 0 {
+            (moving / 2).saturating_mul(moving - 1)
+        } else {
+            moving.saturating_mul((moving - 1) / 2)
+        };
+        moving.saturating_mul(speed)
+            .saturating_sub(lost.saturating_mul(self.deceleration))
+    }
+}
+
+/// The speed reached after holding the button is given by an arbitrary
+/// charge curve. Flat stretches are fine, as long as the distance still grows
+/// and then shrinks, as it does for a single step or for any non-decreasing
+/// concave curve
+pub struct Charge<F: Fn(u128) -> u128>(pub F);
+
+impl<F: Fn(u128) -> u128> BoatModel for Charge<F> {
+    fn distance(&self, duration: u128, time_pressed: u128) -> u128 {
+        duration.saturating_sub(time_pressed).saturating_mul((self.0)(time_pressed))
+    }
+}
+
+/// Finds the shortest and longest presses that travel further than `record`,
+/// for any model whose distance grows and then shrinks. Comparing the
+/// distances of neighbouring presses tells on which side of the peak we are,
+/// which leads to a winning press, and then binary searches on each side of
+/// it find where the record is beaten. Flat stretches below the record give
+/// no such hint, so they are walked to their ends, at a cost linear in their
+/// length.
+///
+/// Returns None if no press beats the record.
+pub fn winning_interval<M: BoatModel + ?Sized>(model: &M, duration: u128, record: u128) -> Option<(u128, u128)> {
+    let distance = |time_pressed| model.distance(duration, time_pressed);
+
+    // If any press wins, there's always one between low and high
+    let (mut low, mut high) = (0u128, duration);
+    let winner = loop {
+        let mid = low + (high - low) / 2;
+        let here = distance(mid);
+        if here > record {
+            break mid
+        }
+        if low == high {
+            return None
+        }
+
+        let next = distance(mid + 1);
+        if here < next {
+            low = mid + 1;
+        } else if here > next {
+            high = mid;
+        } else {
+            let mut left = mid;
+            while left > low && distance(left - 1) == here {
+                left -= 1;
+            }
+            let mut right = mid + 1;
+            while right < high && distance(right + 1) == here {
+                right += 1;
+            }
+            if right < high && distance(right + 1) > here {
+                low = right + 1;
+            } else if left > low && distance(left - 1) > here {
+                high = left - 1;
+            } else {
+                // The stretch is the top, and it doesn't win
+                return None
+            }
+        }
+    };
+
+    // First winning press, on the growing side
+    let (mut low, mut high) = (0u128, winner);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if distance(mid) > record {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    let first = low;
+
+    // Last winning press, on the shrinking side
+    let (mut low, mut high) = (winner, duration);
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        if distance(mid) > record {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    Some((first, low))
+}
@@ -1,100 +1,154 @@
+pub mod boat;
+
+use std::num::ParseIntError;
+
+use num::{BigUint, ToPrimitive};
+
+use boat::{winning_interval, BoatModel};
+
 #[derive(Debug)]
 pub struct RaceInfo {
-    duration: u64, // milliseconds
-    record: u64,   // millimeters
+    duration: u128, // milliseconds
+    record: u128,   // millimeters
 }
 
 impl RaceInfo {
-    fn new(duration: u64, record: u64) -> Self {
+    fn new(duration: u128, record: u128) -> Self {
         RaceInfo { duration, record }
     }
 
     // The boat's speed will be "time_pressed mm/ms". Obviously it will be
     // able to travel only for a maximum of the race duration, meaning that
     // pressing the button for the whole duration (or longer) will result in
-    // exactly 0mm of travel
-    fn distance_travelled(&self, time_pressed: u64) -> u64 {
+    // exactly 0mm of travel. Returns None if the distance doesn't fit in a u128
+    fn distance_travelled(&self, time_pressed: u128) -> Option<u128> {
         let time_travelling = self.duration.saturating_sub(time_pressed);
 
-        time_travelling * time_pressed
+        time_travelling.checked_mul(time_pressed)
     }
 
-    pub fn find_min(&self) -> u64 {
-        let mut lim = (0u64, self.duration / 2);
-        let mut lowest = 0;
+    // A distance too large for a u128 is, by definition, above the record
+    fn beats_record(&self, time_pressed: u128) -> bool {
+        self.distance_travelled(time_pressed)
+            .is_none_or(|distance| distance > self.record)
+    }
 
-        while lim.0 <= lim.1 {
-            let mid = (lim.0 + lim.1) / 2;
-            let d = self.distance_travelled(mid);
+    // Binary search for the shortest winning press. The distance grows
+    // with the time pressed up to half the duration
+    pub fn find_min(&self) -> Option<u128> {
+        let (mut low, mut high) = (0u128, self.duration / 2);
+        if !self.beats_record(high) {
+            return None
+        }
 
-            if d > self.record {
-                lowest = mid;
-                lim = (lim.0, mid - 1)
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.beats_record(mid) {
+                high = mid;
             } else {
-                lim = (mid + 1, lim.1)
+                low = mid + 1;
             }
         }
 
-        lowest
+        Some(low)
     }
 
-    pub fn find_max(&self) -> u64 {
-        let mut highest = self.duration;
-        let mut lim = (self.duration / 2, highest);
-
-        while lim.0 <= lim.1 {
-            let mid = (lim.0 + lim.1) / 2;
-            let d = self.distance_travelled(mid);
+    // Binary search for the longest winning press. The distance decreases
+    // from half the duration onwards
+    pub fn find_max(&self) -> Option<u128> {
+        let (mut low, mut high) = (self.duration / 2, self.duration);
+        if !self.beats_record(low) {
+            return None
+        }
 
-            if d <= self.record {
-                lim = (lim.0, mid - 1)
+        while low < high {
+            let mid = low + (high - low).div_ceil(2);
+            if self.beats_record(mid) {
+                low = mid;
             } else {
-                highest = mid;
-                lim = (mid + 1, lim.1)
+                high = mid - 1;
             }
         }
 
-        highest
+        Some(low)
     }
 
     // Returns two numbers: Minimum and maximum number of seconds the button
-    // can be pressed that will let you beat the record
-    pub fn solve(&self) -> (u64, u64) {
-        (self.find_min(), self.find_max())
+    // can be pressed that will let you beat the record, or None if there's
+    // no way to beat it.
+    //
+    // Pressing for t ms beats the record r in a race of d ms when
+    // t * (d - t) > r, that is, when t lies strictly between the roots of
+    // t^2 - d*t + r = 0: (d ± sqrt(d^2 - 4r)) / 2. The discriminant is
+    // computed with big integers, and the estimate from its integer square
+    // root is then nudged to the exact boundary
+    pub fn solve(&self) -> Option<(u128, u128)> {
+        let duration = BigUint::from(self.duration);
+        let four_record = BigUint::from(self.record) * 4u32;
+        let squared = &duration * &duration;
+        if squared <= four_record {
+            return None
+        }
+        let root = (squared - four_record).sqrt().to_u128().unwrap();
+
+        let mut lowest = (self.duration - root) / 2;
+        while lowest > 0 && self.beats_record(lowest - 1) {
+            lowest -= 1;
+        }
+        while lowest <= self.duration / 2 && !self.beats_record(lowest) {
+            lowest += 1;
+        }
+        if lowest > self.duration / 2 {
+            return None
+        }
+
+        // The distance is symmetric around half the duration
+        Some((lowest, self.duration - lowest))
+    }
+
+    /// Like `solve`, but for any boat model. Uses a generic search instead of
+    /// the closed form, which only holds for the linear model
+    pub fn solve_with<M: BoatModel + ?Sized>(&self, model: &M) -> Option<(u128, u128)> {
+        winning_interval(model, self.duration, self.record)
+    }
+
+    pub fn ways_to_win(&self) -> u128 {
+        self.solve().map_or(0, |(min, max)| max - min + 1)
     }
 }
 
-pub fn parse_problem(time_line: &str, distance_line: &str) -> Vec<RaceInfo> {
-    let times = (time_line.split_once(":").unwrap().1)
+pub fn parse_problem(time_line: &str, distance_line: &str) -> Result<Vec<RaceInfo>, ParseIntError> {
+    let times = (time_line.split_once(':').unwrap().1)
         .split_whitespace()
-        .map(|t| t.parse::<u64>().unwrap());
-    let distances = (distance_line.split_once(":").unwrap().1)
+        .map(|t| t.parse::<u128>());
+    let distances = (distance_line.split_once(':').unwrap().1)
         .split_whitespace()
-        .map(|t| t.parse::<u64>().unwrap());
+        .map(|t| t.parse::<u128>());
 
     times.zip(distances)
-        .map(|(t, d)| RaceInfo::new(t, d))
+        .map(|(t, d)| Ok(RaceInfo::new(t?, d?)))
         .collect()
 }
 
-pub fn parse_kerning(time_line: &str, distance_line: &str) -> RaceInfo {
-    let duration = (time_line.split_once(":").unwrap().1)
+pub fn parse_kerning(time_line: &str, distance_line: &str) -> Result<RaceInfo, ParseIntError> {
+    let duration = (time_line.split_once(':').unwrap().1)
         .split_whitespace()
         .collect::<Vec<_>>()
         .join("")
-        .parse::<u64>().unwrap();
-    let record = (distance_line.split_once(":").unwrap().1)
+        .parse::<u128>()?;
+    let record = (distance_line.split_once(':').unwrap().1)
         .split_whitespace()
         .collect::<Vec<_>>()
         .join("")
-        .parse::<u64>().unwrap();
+        .parse::<u128>()?;
 
-    RaceInfo::new(duration, record)
+    Ok(RaceInfo::new(duration, record))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::RaceInfo;
+    use crate::{parse_kerning, RaceInfo};
+    use crate::boat::{BoatModel, Capped, Charge, Drag, Linear};
 
     fn get_cases() -> Vec<RaceInfo> {
         vec![
@@ -107,13 +161,13 @@ mod tests {
     #[test]
     fn bracketing() {
         let races = get_cases();
-        let lowest: Vec<u64>= races
+        let lowest: Vec<u128>= races
             .iter()
-            .map(|race| race.find_min())
+            .map(|race| race.find_min().unwrap())
             .collect();
-        let highest: Vec<u64>= races
+        let highest: Vec<u128>= races
             .iter()
-            .map(|race| race.find_max())
+            .map(|race| race.find_max().unwrap())
             .collect();
 
         assert_eq!(lowest, [2, 4, 11]);
@@ -123,8 +177,8 @@ mod tests {
     #[test]
     fn distance_travelled() {
         let race = RaceInfo::new(7, 9);
-        let distances: Vec<u64> = (0..10)
-            .map(|t| race.distance_travelled(t))
+        let distances: Vec<u128> = (0..10)
+            .map(|t| race.distance_travelled(t).unwrap())
             .collect();
 
         assert_eq!(
@@ -136,11 +190,119 @@ mod tests {
     #[test]
     fn solve() {
         let cases = get_cases();
-        let solutions: Vec<(u64, u64)> = cases
+        let solutions: Vec<(u128, u128)> = cases
             .iter()
-            .map(|case| case.solve())
+            .map(|case| case.solve().unwrap())
             .collect();
 
         assert_eq!(solutions, vec![(2, 5), (4, 11), (11, 19)])
     }
+
+    #[test]
+    fn closed_form_matches_search() {
+        for duration in 0..60 {
+            for record in 0..(duration * duration / 4 + 3) {
+                let race = RaceInfo::new(duration, record);
+                let searched = race.find_min().zip(race.find_max());
+                assert_eq!(race.solve(), searched, "duration {duration}, record {record}");
+            }
+        }
+    }
+
+    #[test]
+    fn unbeatable_records() {
+        // Pressing for 2ms out of 4 gives exactly 4mm, which doesn't beat it
+        let race = RaceInfo::new(4, 4);
+        assert_eq!(race.solve(), None);
+        assert_eq!(race.find_min(), None);
+        assert_eq!(race.find_max(), None);
+        assert_eq!(race.ways_to_win(), 0);
+
+        assert_eq!(RaceInfo::new(0, 0).solve(), None);
+        assert_eq!(RaceInfo::new(1, 0).solve(), None);
+        assert_eq!(RaceInfo::new(2, 0).solve(), Some((1, 1)));
+    }
+
+    #[test]
+    fn huge_races() {
+        // Way past u64, and squaring the duration overflows a u128
+        let race = parse_kerning(
+            "Time: 1000000000000 000000000000000",
+            "Distance: 1 000000000000000 000000000000000").unwrap();
+        let solution = race.solve();
+        assert!(solution.is_some());
+        assert_eq!(solution, race.find_min().zip(race.find_max()));
+
+        let race = RaceInfo::new(u128::MAX, u128::MAX);
+        assert_eq!(race.solve(), race.find_min().zip(race.find_max()));
+        assert_eq!(race.solve(), Some((2, u128::MAX - 2)));
+
+        assert!(parse_kerning("Time: 1 000000000000000000000000000000000000000", "Distance: 1").is_err());
+    }
+
+    // Reference solution for the models: try every single press
+    fn brute_force<M: BoatModel>(model: &M, duration: u128, record: u128) -> Option<(u128, u128)> {
+        let winners = (0..=duration)
+            .filter(|&pressed| model.distance(duration, pressed) > record)
+            .collect::<Vec<_>>();
+
+        winners.first().copied().zip(winners.last().copied())
+    }
+
+    #[test]
+    fn generic_solver_with_linear_model() {
+        for duration in 0..60 {
+            for record in 0..(duration * duration / 4 + 3) {
+                let race = RaceInfo::new(duration, record);
+                assert_eq!(race.solve_with(&Linear), race.solve(), "duration {duration}, record {record}");
+            }
+        }
+
+        // The peak distance saturates, but the boundaries don't
+        let race = RaceInfo::new(1 << 70, 1 << 100);
+        assert_eq!(race.solve_with(&Linear), race.solve());
+    }
+
+    #[test]
+    fn boat_models() {
+        assert_eq!(Capped { max_speed: 3 }.distance(7, 5), 6);
+        assert_eq!(Drag { deceleration: 2 }.distance(10, 5), 9);
+        assert_eq!(Drag { deceleration: 0 }.distance(10, 5), 25);
+        assert_eq!(Charge(|pressed| pressed * pressed).distance(7, 2), 20);
+
+        for duration in 0..40 {
+            for record in (0..400).step_by(7) {
+                assert_eq!(
+                    RaceInfo::new(duration, record).solve_with(&Capped { max_speed: 5 }),
+                    brute_force(&Capped { max_speed: 5 }, duration, record));
+                assert_eq!(
+                    RaceInfo::new(duration, record).solve_with(&Drag { deceleration: 3 }),
+                    brute_force(&Drag { deceleration: 3 }, duration, record));
+                let curve = Charge(|pressed: u128| pressed * pressed);
+                assert_eq!(
+                    RaceInfo::new(duration, record).solve_with(&curve),
+                    brute_force(&curve, duration, record));
+            }
+        }
+    }
+
+    #[test]
+    fn plateaus() {
+        let step = Charge(|pressed: u128| if pressed < 10 { 0 } else { 1 });
+        assert_eq!(RaceInfo::new(11, 0).solve_with(&step), Some((10, 10)));
+        assert_eq!(RaceInfo::new(12, 0).solve_with(&step), Some((10, 11)));
+        assert_eq!(RaceInfo::new(13, 0).solve_with(&step), Some((10, 12)));
+
+        let saturated = Charge(|pressed: u128| if pressed < 10 { 0 } else { u128::MAX });
+        let late = Charge(|pressed: u128| if pressed < 30 { 0 } else { 50 });
+        let flat = Charge(|_| 2);
+        for duration in 0..50 {
+            for record in 0..60 {
+                assert_eq!(RaceInfo::new(duration, record).solve_with(&step), brute_force(&step, duration, record));
+                assert_eq!(RaceInfo::new(duration, record).solve_with(&saturated), brute_force(&saturated, duration, record));
+                assert_eq!(RaceInfo::new(duration, record).solve_with(&late), brute_force(&late, duration, record));
+                assert_eq!(RaceInfo::new(duration, record).solve_with(&flat), brute_force(&flat, duration, record));
+            }
+        }
+    }
 }
@@ -1,19 +1,30 @@
+use std::env;
 use std::io::stdin;
 
-use day07::Hand;
+use anyhow::Result;
 
-fn main() {
-    let mut hands: Vec<Hand> = stdin().lines()
-        .map(|line| Hand::from_str(line.unwrap().as_str(), false))
-        .collect();
+use day07::{Hand, Rules};
+use day07::options::Options;
+use day07::policy::{Comparison, Listing};
 
-    hands.sort();
+fn main() -> Result<()> {
+    let options = Options::parse(env::args().skip(1))?;
+    let policy = options.policy;
 
-    let winnings = hands.into_iter()
-        .enumerate()
-        .fold(0, |acc, (n, hand)| {
-            acc + (hand.bid * ((n as u64) + 1))
-        });
+    let rules = Rules::standard();
+    let hands = stdin().lines()
+        .map(|line| Hand::parse(&line?, &rules))
+        .collect::<Result<Vec<_>>>()?;
 
-    println!("Total winnings: {winnings}");
+    if options.list {
+        print!("{}", Listing::new(&hands, policy));
+    }
+
+    println!("Total winnings: {}", policy.winnings(&hands));
+
+    if let Some(other) = options.compare {
+        print!("{}", Comparison::new(&hands, policy, other));
+    }
+
+    Ok(())
 }
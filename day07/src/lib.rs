@@ -1,204 +1,450 @@
-use std::{collections::HashMap, fmt::Display};
+pub mod options;
+pub mod policy;
 
-use anyhow::bail;
+use std::{cmp::Ordering, collections::{HashMap, HashSet}, fmt::Display, str::FromStr};
 
+use anyhow::{bail, Result};
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
-enum Kind {
-    High,
-    Pair,
-    TwoPair,
-    ThreeOf,
-    Full,
-    FourOf,
-    FiveOf,
-}
-
-#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
-enum Card {
-    Joker,
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
-    Nine,
-    Ten,
-    Jack,
-    Queen,
-    King,
-    Ace,
+/// A kind of hand, defined by the sizes of the groups of equal cards it
+/// needs. Single cards are left out, so a full house is [3, 2] and a high
+/// card is []. The order of the groups doesn't matter
+#[derive(Debug, Clone, PartialEq)]
+pub struct KindDef {
+    pub name: String,
+    pub groups: Vec<usize>,
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
-pub struct Hand {
-    kind: Kind,
-    cards: Vec<Card>,
-    pub bid: u64,
+impl KindDef {
+    pub fn new(name: &str, groups: &[usize]) -> Self {
+        let mut groups = groups.to_vec();
+        groups.sort_by(|a, b| b.cmp(a));
+
+        KindDef { name: name.to_string(), groups }
+    }
 }
 
-impl Hand {
-    pub fn from_str(value: &str, with_joker: bool) -> Hand {
-        let (hand_raw, bid) = value.split_once(' ').unwrap();
-        let cards: Vec<Card> = hand_raw.chars().map(|f| Card::from_char(f, with_joker).unwrap()).collect();
-        let kind = Kind::from_cards(&cards).unwrap();
-        let bid = bid.parse::<u64>().unwrap();
+/// Configures a variant of the game: the size of the hands, the faces from
+/// lowest to highest rank, which faces are wild, and which kinds of hands
+/// exist, also from lowest to highest
+#[derive(Debug, Clone)]
+pub struct Rules {
+    hand_size: usize,
+    order: Vec<char>,
+    wild: HashSet<char>,
+    kinds: Vec<KindDef>,
+}
 
-        Hand {
-            kind,
-            cards,
-            bid
+impl Rules {
+    pub fn new(hand_size: usize, order: &str, wild: &str, mut kinds: Vec<KindDef>) -> Result<Self> {
+        let order = order.chars().collect::<Vec<_>>();
+        let unique = order.iter().collect::<HashSet<_>>();
+        if unique.len() != order.len() {
+            bail!("Repeated faces in the card order")
         }
+        let wild = wild.chars().collect::<HashSet<_>>();
+        if let Some(face) = wild.iter().find(|face| !unique.contains(face)) {
+            bail!("Wild face {face:?} is not in the card order")
+        }
+        if let Some(kind) = kinds.iter().find(|kind| kind.groups.iter().sum::<usize>() > hand_size) {
+            bail!("Kind {} needs more than {hand_size} cards", kind.name)
+        }
+        // Classifying matches the largest groups first, whatever order they
+        // were given in
+        for kind in kinds.iter_mut() {
+            kind.groups.sort_by(|a, b| b.cmp(a));
+        }
+
+        Ok(Rules { hand_size, order, wild, kinds })
     }
-}
 
-impl ToString for Card {
-    fn to_string(&self) -> String {
-        match self {
-            Card::Joker => 'J',
-            Card::Two => '2',
-            Card::Three => '3',
-            Card::Four => '4',
-            Card::Five => '5',
-            Card::Six => '6',
-            Card::Seven => '7',
-            Card::Eight => '8',
-            Card::Nine => '9',
-            Card::Ten => 'T',
-            Card::Jack => 'j',
-            Card::Queen => 'Q',
-            Card::King => 'K',
-            Card::Ace => 'A',
-        }.to_string()
+    /// The usual kinds of hand for 5 cards
+    pub fn standard_kinds() -> Vec<KindDef> {
+        vec![
+            KindDef::new("High", &[]),
+            KindDef::new("Pair", &[2]),
+            KindDef::new("TwoPair", &[2, 2]),
+            KindDef::new("ThreeOf", &[3]),
+            KindDef::new("Full", &[3, 2]),
+            KindDef::new("FourOf", &[4]),
+            KindDef::new("FiveOf", &[5]),
+        ]
     }
-}
 
-impl Display for Hand {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} => {:?}",
-               self.cards
-                   .iter()
-                   .map(|c| c.to_string())
-                   .collect::<Vec<_>>()
-                   .join(""),
-               self.kind)
+    /// Camel Cards, as in part 1
+    pub fn standard() -> Self {
+        Rules::new(5, "23456789TJQKA", "", Rules::standard_kinds()).unwrap()
     }
-}
 
-impl Kind {
-    fn from_cards(cards: &[Card]) -> anyhow::Result<Kind> {
-        if cards.len() != 5 {
-            bail!("Illegal hand size: {}", cards.len())
+    /// Camel Cards with jokers, as in part 2: 'J' is wild, and the lowest card
+    pub fn jokers() -> Self {
+        Rules::new(5, "J23456789TQKA", "J", Rules::standard_kinds()).unwrap()
+    }
+
+    pub fn hand_size(&self) -> usize {
+        self.hand_size
+    }
+
+    pub fn kinds(&self) -> &[KindDef] {
+        &self.kinds
+    }
+
+    fn card(&self, face: char) -> Result<Card> {
+        match self.order.iter().position(|&known| known == face) {
+            Some(rank) => Ok(Card { face, rank }),
+            None => bail!("Not a valid card face {face:?}"),
         }
+    }
 
-        let mut unique = HashMap::new();
-        let mut jokers = 0;
-        // Don't include the jokers in the mapping
-        for card in cards {
-            if *card == Card::Joker {
-                jokers += 1;
+    // Picks the best kind for the cards, letting the wild ones count as any
+    // other face. Returns the kind, along with the cards that the wild ones
+    // imitate to reach it
+    fn classify(&self, cards: &[Card]) -> Result<(Kind, Vec<Card>)> {
+        let mut counts: HashMap<Card, usize> = HashMap::new();
+        let mut wilds = 0;
+        for &card in cards {
+            if self.wild.contains(&card.face) {
+                wilds += 1;
+            } else {
+                *counts.entry(card).or_insert(0) += 1;
             }
+        }
+        // Wild cards join the highest ranked among the equally large groups
+        let mut histogram = counts.into_iter().collect::<Vec<_>>();
+        histogram.sort_by(|(card_a, count_a), (card_b, count_b)| {
+            count_b.cmp(count_a).then(card_b.cmp(card_a))
+        });
 
-            let count = unique.entry(card).or_insert(0);
-            *count += 1;
+        // Matching the largest groups needed with the largest ones available
+        // minimizes the number of wild cards needed to fill the gaps
+        for (rank, kind) in self.kinds.iter().enumerate().rev() {
+            let missing = kind.groups.iter()
+                .enumerate()
+                .map(|(idx, &size)| size.saturating_sub(histogram.get(idx).map_or(0, |&(_, count)| count)))
+                .sum::<usize>();
+            if missing <= wilds {
+                let imitated = self.imitate(kind, &histogram, wilds);
+                return Ok((Kind { rank, name: kind.name.clone() }, imitated))
+            }
         }
 
-        let mut card_count = unique.values().cloned().collect::<Vec<_>>();
-        card_count.sort();
-
-
-        Ok(match unique.len() {
-            5 => if jokers == 0 { Kind::High } else { Kind::Pair },
-            // For the following case, jokers could be 1 or 2. In either
-            // case we can call Three of a Kind
-            4 => if jokers == 0 { Kind::Pair } else { Kind::ThreeOf }
-            3 => if card_count == [1, 2, 2] {
-                match jokers {
-                    0 => Kind::TwoPair,
-                    1 => Kind::Full,
-                    // The remaining case: there are 2 jokers
-                    _ => Kind::FourOf,
-                }
-            } else { // Has to be [1, 1, 3]
-                match jokers {
-                    0 => Kind::ThreeOf,
-                    // The remaining case: there are either 1 or 3 jokers,
-                    // the result is the same
-                    _ => Kind::FourOf
-                }
+        bail!("The hand doesn't match any kind")
+    }
+
+    // Chooses the card each wild one stands for, given a kind that they can
+    // reach. Groups with no cards in the hand, and wild cards left over, take
+    // the highest faces that are not in the hand yet
+    fn imitate(&self, kind: &KindDef, histogram: &[(Card, usize)], wilds: usize) -> Vec<Card> {
+        let mut fresh = self.order.iter()
+            .enumerate()
+            .rev()
+            .map(|(rank, &face)| Card { face, rank })
+            .filter(|card| !self.wild.contains(&card.face))
+            .filter(|card| histogram.iter().all(|(other, _)| other != card));
+
+        let mut imitated = vec![];
+        for (idx, &size) in kind.groups.iter().enumerate() {
+            let (card, count) = match histogram.get(idx) {
+                Some(&group) => group,
+                None => match fresh.next() {
+                    Some(card) => (card, 0),
+                    None => break,
+                },
+            };
+            imitated.extend(std::iter::repeat_n(card, size.saturating_sub(count)));
+        }
+        while imitated.len() < wilds {
+            match fresh.next().or(histogram.first().map(|&(card, _)| card)) {
+                Some(card) => imitated.push(card),
+                None => break,
             }
-            2 => if jokers == 0 {
-                if card_count == [1, 4] { Kind::FourOf } else { Kind::Full }
+        }
+
+        imitated
+    }
+}
+
+/// The kind of a hand, ranked according to the rules it was parsed with
+#[derive(Debug, Clone)]
+pub struct Kind {
+    rank: usize,
+    name: String,
+}
+
+impl Kind {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PartialEq for Kind {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank == other.rank
+    }
+}
+
+impl Eq for Kind {}
+
+impl PartialOrd for Kind {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Kind {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank.cmp(&other.rank)
+    }
+}
+
+/// A card, ranked according to the rules it was parsed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Card {
+    face: char,
+    rank: usize,
+}
+
+impl PartialOrd for Card {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Card {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank.cmp(&other.rank)
+    }
+}
+
+impl Display for Card {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.face)
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Hand {
+    kind: Kind,
+    cards: Vec<Card>,
+    resolved: Vec<Card>,
+    pub bid: u64,
+}
+
+impl Hand {
+    pub fn parse_with_jokers(value: &str, with_joker: bool) -> Hand {
+        let rules = if with_joker { Rules::jokers() } else { Rules::standard() };
+
+        Hand::parse(value, &rules).unwrap()
+    }
+
+    pub fn parse(value: &str, rules: &Rules) -> Result<Hand> {
+        let Some((hand_raw, bid)) = value.split_once(' ') else {
+            bail!("Expected a hand and a bid: {value:?}")
+        };
+        let cards = hand_raw.chars()
+            .map(|face| rules.card(face))
+            .collect::<Result<Vec<_>>>()?;
+        if cards.len() != rules.hand_size {
+            bail!("Illegal hand size: {}", cards.len())
+        }
+        let (kind, imitated) = rules.classify(&cards)?;
+        let mut imitated = imitated.into_iter();
+        let resolved = cards.iter()
+            .map(|&card| if rules.wild.contains(&card.face) {
+                imitated.next().unwrap_or(card)
             } else {
-                Kind::FiveOf
-            }
-            1 => Kind::FiveOf,
-            _ => unimplemented!() // Can't happen
+                card
+            })
+            .collect();
+        let bid = bid.parse::<u64>()?;
+
+        Ok(Hand {
+            kind,
+            cards,
+            resolved,
+            bid
         })
     }
+
+    pub fn kind(&self) -> &Kind {
+        &self.kind
+    }
+
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
+
+    /// The cards after replacing the wild ones with those they imitate to
+    /// reach the kind of the hand
+    pub fn resolved(&self) -> &[Card] {
+        &self.resolved
+    }
+
+    /// Positions of the wild cards, with the card each one stands for
+    pub fn substitutions(&self) -> Vec<(usize, Card)> {
+        self.cards.iter()
+            .zip(&self.resolved)
+            .enumerate()
+            .filter(|(_, (card, resolved))| card.face != resolved.face)
+            .map(|(idx, (_, &resolved))| (idx, resolved))
+            .collect()
+    }
 }
 
-impl Card {
-    fn from_char(value: char, with_joker: bool) -> anyhow::Result<Self> {
-        Ok(match value {
-            'J' => if with_joker { Card::Joker } else { Card::Jack },
-            '2' => Card::Two,
-            '3' => Card::Three,
-            '4' => Card::Four,
-            '5' => Card::Five,
-            '6' => Card::Six,
-            '7' => Card::Seven,
-            '8' => Card::Eight,
-            '9' => Card::Nine,
-            'T' => Card::Ten,
-            'Q' => Card::Queen,
-            'K' => Card::King,
-            'A' => Card::Ace,
-            _ => bail!("Not a valid card face {value:?}")
-        })
+/// Prints the hand back as it was read, so that parsing the output with the
+/// same rules gives the same hand
+impl Display for Hand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for card in &self.cards {
+            write!(f, "{card}")?;
+        }
+        write!(f, " {}", self.bid)
     }
 }
 
-impl Hand {
-    #[allow(dead_code)]
-    fn new(kind: Kind, cards: Vec<Card>, bid: u64) -> Self {
-        Hand { kind, cards, bid }
+/// Parses a hand with the standard rules. Use `Hand::parse` for others
+impl FromStr for Hand {
+    type Err = anyhow::Error;
+
+    fn from_str(st: &str) -> Result<Self> {
+        Hand::parse(st, &Rules::standard())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Card, Hand, Kind};
+    use crate::{Hand, KindDef, Rules};
 
     const SAMPLE_INPUT: &str = include_str!("../input.small");
 
+    fn describe(hand: &Hand) -> (String, &str, u64) {
+        (
+            hand.cards().iter().map(|card| card.to_string()).collect(),
+            hand.kind().name(),
+            hand.bid,
+        )
+    }
+
     #[test]
     fn parse_line () {
         let line = "32T3K 765";
-        let hand = Hand::from_str(line, false);
+        let hand = Hand::parse_with_jokers(line, false);
 
-        assert_eq!(hand, Hand::new(
-                    Kind::Pair,
-                    vec![Card::Three, Card::Two, Card::Ten, Card::Three, Card::King],
-                    765));
+        assert_eq!(describe(&hand), ("32T3K".to_string(), "Pair", 765));
 
         let line = "QQQJA 483";
-        let hand = Hand::from_str(line, false);
+        let hand = Hand::parse_with_jokers(line, false);
 
-        assert_eq!(hand, Hand::new(
-                    Kind::ThreeOf,
-                    vec![Card::Queen, Card::Queen, Card::Queen, Card::Jack, Card::Ace],
-                    483));
+        assert_eq!(describe(&hand), ("QQQJA".to_string(), "ThreeOf", 483));
     }
 
     #[test]
     fn compare_hands () {
         let hands: Vec<Hand> = SAMPLE_INPUT.lines()
-            .map(|line| Hand::from_str(line, false))
+            .map(|line| Hand::parse_with_jokers(line, false))
             .collect();
 
         assert!(hands[1] > hands[0]);
     }
+
+    #[test]
+    fn joker_kinds() {
+        let kinds = SAMPLE_INPUT.lines()
+            .map(|line| Hand::parse_with_jokers(line, true).kind().name().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, ["Pair", "FourOf", "TwoPair", "FourOf", "FourOf"]);
+
+        for (line, kind) in [("JJJJJ 1", "FiveOf"), ("JJJJ2 1", "FiveOf"), ("2345J 1", "Pair"), ("22JJ3 1", "FourOf"), ("223J3 1", "Full")] {
+            assert_eq!(Hand::parse_with_jokers(line, true).kind().name(), kind);
+        }
+
+        // Jokers are the weakest card
+        assert!(Hand::parse_with_jokers("JKKK2 1", true) < Hand::parse_with_jokers("QQQQ2 1", true));
+        assert!(Hand::parse_with_jokers("JKKK2 1", false) > Hand::parse_with_jokers("TKKK2 1", false));
+    }
+
+    #[test]
+    fn variant_rules() {
+        let mut kinds = Rules::standard_kinds();
+        kinds.insert(3, KindDef::new("ThreePair", &[2, 2, 2]));
+        kinds.insert(6, KindDef::new("TwoThrees", &[3, 3]));
+        kinds.push(KindDef::new("SixOf", &[6]));
+        let rules = Rules::new(6, "23456789TJQKA", "J2", kinds).unwrap();
+
+        let kind = |line| Hand::parse(line, &rules).unwrap().kind().name().to_string();
+        assert_eq!(kind("334455 1"), "ThreePair");
+        assert_eq!(kind("33445J 1"), "Full");
+        assert_eq!(kind("333444 1"), "TwoThrees");
+        assert_eq!(kind("33344J 1"), "FourOf");
+        assert_eq!(kind("3J4567 1"), "Pair");
+        assert_eq!(kind("JJ2QQQ 1"), "SixOf");
+        assert_eq!(kind("345678 1"), "High");
+
+        assert!(Hand::parse("33445 1", &rules).is_err());
+        assert!(Hand::parse("3344XX 1", &rules).is_err());
+        assert!(Rules::new(5, "23456789TJQKA", "X", Rules::standard_kinds()).is_err());
+        assert!(Rules::new(4, "23456789TJQKA", "", Rules::standard_kinds()).is_err());
+
+        // Groups can be listed in any order
+        let mut kinds = Rules::standard_kinds();
+        kinds[4] = KindDef::new("Full house", &[2, 3]);
+        kinds[2] = KindDef { name: "Two pairs".to_string(), groups: vec![1, 2, 2] };
+        let rules = Rules::new(5, "23456789TJQKA", "", kinds).unwrap();
+        assert_eq!(Hand::parse("KKK77 1", &rules).unwrap().kind().name(), "Full house");
+        assert_eq!(Hand::parse("KK677 1", &rules).unwrap().kind().name(), "Two pairs");
+        assert_eq!(KindDef::new("Full house", &[2, 3]).groups, [3, 2]);
+
+        // Without two pairs, they rank as a single pair
+        let kinds = Rules::standard_kinds().into_iter()
+            .filter(|kind| kind.name != "TwoPair")
+            .collect();
+        let rules = Rules::new(5, "23456789TJQKA", "", kinds).unwrap();
+        assert_eq!(Hand::parse("KK677 28", &rules).unwrap().kind().name(), "Pair");
+    }
+
+    #[test]
+    fn joker_resolution() {
+        let resolve = |line| {
+            let hand = Hand::parse_with_jokers(line, true);
+            (hand.resolved().iter().map(|card| card.to_string()).collect::<String>(), hand.substitutions())
+        };
+
+        let (resolved, substitutions) = resolve("KTJJT 220");
+        assert_eq!(resolved, "KTTTT");
+        assert_eq!(substitutions.iter().map(|&(idx, card)| (idx, card.to_string())).collect::<Vec<_>>(),
+                   [(2, "T".to_string()), (3, "T".to_string())]);
+
+        assert_eq!(resolve("JJJJJ 1").0, "AAAAA");
+        assert_eq!(resolve("2345J 1").0, "23455");
+        assert_eq!(resolve("22J33 1").0, "22333");
+        assert_eq!(resolve("32T3K 765"), ("32T3K".to_string(), vec![]));
+
+        // Without jokers, nothing gets replaced
+        let hand = Hand::parse_with_jokers("KTJJT 220", false);
+        assert_eq!(hand.resolved(), hand.cards());
+        assert!(hand.substitutions().is_empty());
+
+        // Every resolved hand is, without wild cards, of the same kind
+        for line in ["JJJJ2 1", "J2J3J 1", "2J4J6 1", "TTJ99 1", "AJ2KQ 1"] {
+            let hand = Hand::parse_with_jokers(line, true);
+            let resolved = format!("{} 1", hand.resolved().iter().map(|card| card.to_string()).collect::<String>());
+            assert_eq!(Hand::parse_with_jokers(&resolved, false).kind().name(), hand.kind().name());
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for line in SAMPLE_INPUT.lines().chain(["JJJJJ 0", "J2J3J 18446744073709551615"]) {
+            for rules in [Rules::standard(), Rules::jokers()] {
+                let hand = Hand::parse(line, &rules).unwrap();
+                assert_eq!(hand.to_string(), line);
+                assert_eq!(Hand::parse(&hand.to_string(), &rules).unwrap(), hand);
+            }
+            let hand = line.parse::<Hand>().unwrap();
+            assert_eq!(hand.to_string().parse::<Hand>().unwrap(), hand);
+        }
+
+        assert_eq!(<Hand as std::str::FromStr>::from_str("32T3K 765").unwrap(), Hand::parse_with_jokers("32T3K 765", false));
+        assert!("32T3K".parse::<Hand>().is_err());
+        assert!("32T3K bid".parse::<Hand>().is_err());
+    }
 }
@@ -0,0 +1,58 @@
+use anyhow::{bail, Result};
+
+use crate::policy::Policy;
+
+/// Command line options for both parts: `[POLICY] [--compare POLICY] [--list]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    pub policy: Policy,
+    pub compare: Option<Policy>,
+    pub list: bool,
+}
+
+impl Options {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut options = Options { policy: Policy::Positional, compare: None, list: false };
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--compare" => {
+                    let Some(name) = args.next() else {
+                        bail!("--compare needs a policy")
+                    };
+                    options.compare = Some(parse_policy(&name)?);
+                }
+                "--list" => options.list = true,
+                name => options.policy = parse_policy(name)?,
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_policy(name: &str) -> Result<Policy> {
+    name.parse::<Policy>().map_err(anyhow::Error::msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Options;
+    use crate::policy::Policy;
+
+    fn parse(args: &[&str]) -> anyhow::Result<Options> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn options() {
+        assert_eq!(parse(&[]).unwrap(), Options { policy: Policy::Positional, compare: None, list: false });
+        assert_eq!(parse(&["poker", "--compare", "sorted", "--list"]).unwrap(),
+                   Options { policy: Policy::Poker, compare: Some(Policy::Sorted), list: true });
+
+        assert!(parse(&["random"]).is_err());
+        assert!(parse(&["--compare"]).is_err());
+        assert!(parse(&["--compare", "random"]).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+use std::{cmp::Ordering, collections::HashMap, fmt::Display, str::FromStr};
+
+use crate::{Card, Hand};
+
+/// How to break ties between hands of the same kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    /// Compare the cards one by one, in the order they were dealt. The
+    /// Camel Cards rule
+    Positional,
+    /// Compare the ranks of the groups of equal cards, largest group first,
+    /// and then the kickers from highest to lowest, as in poker. Wild cards
+    /// count as the card they stand for, as they do to decide the kind
+    Poker,
+    /// Compare the cards sorted from highest to lowest. Wild cards keep their
+    /// own rank, as they do under the positional rule
+    Sorted,
+}
+
+impl Policy {
+    /// Orders two hands. Hands that are still tied under the policy fall
+    /// back to the positional order, so that every policy is a total order
+    pub fn compare(&self, a: &Hand, b: &Hand) -> Ordering {
+        let tie_break = match self {
+            Policy::Positional => Ordering::Equal,
+            Policy::Poker => poker_key(a.resolved()).cmp(&poker_key(b.resolved())),
+            Policy::Sorted => sorted_key(a.cards()).cmp(&sorted_key(b.cards())),
+        };
+
+        a.kind().cmp(b.kind())
+            .then(tie_break)
+            .then_with(|| a.cmp(b))
+    }
+
+    /// Indices of the hands, from the weakest to the strongest
+    pub fn rank(&self, hands: &[Hand]) -> Vec<usize> {
+        let mut order = (0..hands.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| self.compare(&hands[a], &hands[b]));
+
+        order
+    }
+
+    pub fn winnings(&self, hands: &[Hand]) -> u64 {
+        self.rank(hands).into_iter()
+            .enumerate()
+            .fold(0, |acc, (n, idx)| {
+                acc + (hands[idx].bid * ((n as u64) + 1))
+            })
+    }
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(st: &str) -> Result<Self, Self::Err> {
+        match st {
+            "positional" => Ok(Policy::Positional),
+            "poker" => Ok(Policy::Poker),
+            "sorted" => Ok(Policy::Sorted),
+            _ => Err(format!("Unknown ordering policy {st}. Use one of 'positional', 'poker' or 'sorted'")),
+        }
+    }
+}
+
+impl Display for Policy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Policy::Positional => "positional",
+            Policy::Poker => "poker",
+            Policy::Sorted => "sorted",
+        };
+        write!(f, "{name}")
+    }
+}
+
+fn poker_key(cards: &[Card]) -> Vec<Card> {
+    let mut counts: HashMap<Card, usize> = HashMap::new();
+    for &card in cards {
+        *counts.entry(card).or_insert(0) += 1;
+    }
+    let mut groups = counts.into_iter().collect::<Vec<_>>();
+    groups.sort_by(|(card_a, count_a), (card_b, count_b)| {
+        count_b.cmp(count_a).then(card_b.cmp(card_a))
+    });
+
+    groups.into_iter().map(|(card, _)| card).collect()
+}
+
+fn sorted_key(cards: &[Card]) -> Vec<Card> {
+    let mut sorted = cards.to_vec();
+    sorted.sort_by(|a, b| b.cmp(a));
+
+    sorted
+}
+
+/// A hand that moves to a different rank when switching policies. Ranks
+/// start at 1 for the weakest hand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RankChange {
+    pub index: usize,
+    pub before: usize,
+    pub after: usize,
+}
+
+/// The hands ranked under a policy, from the weakest to the strongest, with
+/// the cards the wild ones stand for and what each hand wins
+pub struct Listing<'a> {
+    hands: &'a [Hand],
+    policy: Policy,
+}
+
+impl<'a> Listing<'a> {
+    pub fn new(hands: &'a [Hand], policy: Policy) -> Self {
+        Listing { hands, policy }
+    }
+}
+
+impl Display for Listing<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cards = |cards: &[Card]| cards.iter().map(|card| card.to_string()).collect::<String>();
+
+        writeln!(f, "{:>5} {:>6} {:>8} {:>8} {:>12}", "rank", "hand", "resolved", "kind", "winnings")?;
+        for (n, idx) in self.policy.rank(self.hands).into_iter().enumerate() {
+            let hand = &self.hands[idx];
+            writeln!(f, "{:>5} {:>6} {:>8} {:>8} {:>12}",
+                     n + 1, cards(hand.cards()), cards(hand.resolved()), hand.kind().name(),
+                     hand.bid * (n as u64 + 1))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compares the winnings under two policies, and lists the hands that change
+/// rank when switching from the first to the second
+pub struct Comparison<'a> {
+    hands: &'a [Hand],
+    from: Policy,
+    to: Policy,
+}
+
+impl<'a> Comparison<'a> {
+    pub fn new(hands: &'a [Hand], from: Policy, to: Policy) -> Self {
+        Comparison { hands, from, to }
+    }
+}
+
+impl Display for Comparison<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Total winnings ({}): {}", self.to, self.to.winnings(self.hands))?;
+        for change in rank_changes(self.hands, self.from, self.to) {
+            let hand = &self.hands[change.index];
+            writeln!(f, "{hand} ({}): rank {} -> {}", hand.kind().name(), change.before, change.after)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Lists the hands that get a different rank under `to` than under `from`,
+/// in input order
+pub fn rank_changes(hands: &[Hand], from: Policy, to: Policy) -> Vec<RankChange> {
+    let ranks = |policy: Policy| {
+        let mut ranks = vec![0; hands.len()];
+        for (n, idx) in policy.rank(hands).into_iter().enumerate() {
+            ranks[idx] = n + 1;
+        }
+        ranks
+    };
+
+    ranks(from).into_iter()
+        .zip(ranks(to))
+        .enumerate()
+        .filter(|(_, (before, after))| before != after)
+        .map(|(index, (before, after))| RankChange { index, before, after })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rank_changes, Comparison, Listing, Policy, RankChange};
+    use crate::Hand;
+
+    const SAMPLE_INPUT: &str = include_str!("../input.small");
+
+    fn hands(lines: &[&str]) -> Vec<Hand> {
+        lines.iter().map(|line| Hand::parse_with_jokers(line, false)).collect()
+    }
+
+    #[test]
+    fn sample_winnings() {
+        let sample = SAMPLE_INPUT.lines()
+            .map(|line| Hand::parse_with_jokers(line, false))
+            .collect::<Vec<_>>();
+        assert_eq!(Policy::Positional.winnings(&sample), 6440);
+
+        let mut sorted = SAMPLE_INPUT.lines()
+            .map(|line| Hand::parse_with_jokers(line, false))
+            .collect::<Vec<_>>();
+        sorted.sort();
+        let ranked = Policy::Positional.rank(&sample)
+            .into_iter()
+            .map(|idx| &sample[idx])
+            .collect::<Vec<_>>();
+        assert_eq!(ranked, sorted.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn tie_breaks() {
+        // A full house of low triples with high pair, and the other way around
+        let full = hands(&["22AAA 1", "AA222 2"]);
+        assert_eq!(Policy::Positional.rank(&full), [0, 1]);
+        assert_eq!(Policy::Poker.rank(&full), [1, 0]);
+        assert_eq!(Policy::Sorted.rank(&full), [1, 0]);
+
+        // Poker looks at the pair first, sorting at the highest card
+        let pairs = hands(&["33A45 1", "22AK4 2"]);
+        assert_eq!(Policy::Positional.rank(&pairs), [1, 0]);
+        assert_eq!(Policy::Poker.rank(&pairs), [1, 0]);
+        assert_eq!(Policy::Sorted.rank(&pairs), [0, 1]);
+
+        let kickers = hands(&["K2345 1", "2345A 2"]);
+        assert_eq!(Policy::Positional.rank(&kickers), [1, 0]);
+        assert_eq!(Policy::Poker.rank(&kickers), [0, 1]);
+        assert_eq!(Policy::Sorted.rank(&kickers), [0, 1]);
+
+        // Jokers join the group they stand for, but stay weak when sorting
+        let jokers = ["JJ22A 1", "2222K 2"].iter()
+            .map(|line| Hand::parse_with_jokers(line, true))
+            .collect::<Vec<_>>();
+        assert_eq!(Policy::Positional.rank(&jokers), [0, 1]);
+        assert_eq!(Policy::Poker.rank(&jokers), [1, 0]);
+        assert_eq!(Policy::Sorted.rank(&jokers), [1, 0]);
+
+        let jokers = ["JJAAK 1", "QAAAJ 2"].iter()
+            .map(|line| Hand::parse_with_jokers(line, true))
+            .collect::<Vec<_>>();
+        assert_eq!(Policy::Positional.rank(&jokers), [0, 1]);
+        assert_eq!(Policy::Poker.rank(&jokers), [1, 0]);
+        assert_eq!(Policy::Sorted.rank(&jokers), [0, 1]);
+
+        // The kind always comes first
+        let kinds = hands(&["AAKQT 1", "22334 2"]);
+        for policy in [Policy::Positional, Policy::Poker, Policy::Sorted] {
+            assert_eq!(policy.rank(&kinds), [0, 1]);
+        }
+    }
+
+    #[test]
+    fn changes() {
+        let full = hands(&["32T3K 1", "22AAA 2", "AA222 3"]);
+        assert_eq!(rank_changes(&full, Policy::Positional, Policy::Positional), []);
+        assert_eq!(rank_changes(&full, Policy::Positional, Policy::Poker), [
+            RankChange { index: 1, before: 2, after: 3 },
+            RankChange { index: 2, before: 3, after: 2 },
+        ]);
+
+        assert_eq!(Comparison::new(&full, Policy::Positional, Policy::Poker).to_string(),
+                   "Total winnings (poker): 13\n22AAA 2 (Full): rank 2 -> 3\nAA222 3 (Full): rank 3 -> 2\n");
+    }
+
+    #[test]
+    fn listing() {
+        let sample = SAMPLE_INPUT.lines()
+            .map(|line| Hand::parse_with_jokers(line, true))
+            .collect::<Vec<_>>();
+        let listing = Listing::new(&sample, Policy::Positional).to_string();
+        let lines = listing.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], " rank   hand resolved     kind     winnings");
+        assert_eq!(lines[3], "    3  T55J5    T5555   FourOf         2052");
+        assert_eq!(lines[5], "    5  KTJJT    KTTTT   FourOf         1100");
+    }
+
+    #[test]
+    fn parse_policy() {
+        for policy in [Policy::Positional, Policy::Poker, Policy::Sorted] {
+            assert_eq!(policy.to_string().parse::<Policy>(), Ok(policy));
+        }
+        assert!("random".parse::<Policy>().is_err());
+    }
+}
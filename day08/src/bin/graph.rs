@@ -0,0 +1,32 @@
+use std::env;
+use std::io::BufReader;
+use std::io::stdin;
+use std::process::exit;
+
+use day08::parse_instructions;
+
+fn main() {
+    let format = env::args().nth(1).unwrap_or("dot".to_string());
+
+    let problem = parse_instructions(BufReader::new(stdin())).unwrap();
+
+    match format.as_str() {
+        "dot" => print!("{}", problem.to_dot('A', 'Z')),
+        "report" => {
+            let components = problem.components();
+            let largest = components.iter().map(|component| component.len()).max().unwrap_or(0);
+            println!("{} nodes, {} strongly connected components (the largest has {largest} nodes)",
+                     problem.len(), components.len());
+            for start in problem.node_set(|s| s.ends_with('A')).iter() {
+                let cycle = problem.cycle(start);
+                let ends = cycle.iter().filter(|&&id| problem.name(id).ends_with('Z')).count();
+                println!("{}: reaches {} nodes, cycles every {} steps, {ends} of them at an end node",
+                         problem.name(start), problem.reachable(start).iter().count(), cycle.len());
+            }
+        }
+        other => {
+            eprintln!("Unknown format {other}. Use either 'dot' or 'report'");
+            exit(1);
+        }
+    }
+}
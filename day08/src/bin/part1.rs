@@ -4,9 +4,10 @@ use std::io::stdin;
 use day08::parse_instructions;
 
 fn main() {
-    let problem = parse_instructions(BufReader::new(stdin()));
+    let problem = parse_instructions(BufReader::new(stdin())).unwrap();
 
-    let steps = problem.iterate("AAA", |s| { s == "ZZZ" });
-
-    println!("It took {} steps to find the ZZZ node", steps);
+    match problem.iterate("AAA", |s| { s == "ZZZ" }) {
+        Ok(steps) => println!("It took {} steps to find the ZZZ node", steps),
+        Err(err) => println!("{err}"),
+    }
 }
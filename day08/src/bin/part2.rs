@@ -4,9 +4,10 @@ use std::io::stdin;
 use day08::parse_instructions;
 
 fn main() {
-    let problem = parse_instructions(BufReader::new(stdin()));
+    let problem = parse_instructions(BufReader::new(stdin())).unwrap();
 
-    let steps = problem.parallel_iterate('A', 'Z');
-
-    println!("It took {steps} steps to get all the ghosts simultaneously at the end of the path");
+    match problem.parallel_iterate('A', 'Z') {
+        Some(steps) => println!("It took {steps} steps to get all the ghosts simultaneously at the end of the path"),
+        None => println!("The ghosts never get to the end of the path at the same time"),
+    }
 }
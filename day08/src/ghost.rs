@@ -0,0 +1,147 @@
+use num::Integer;
+
+/// The walk of a single ghost, split into the steps it takes before falling
+/// into a cycle and the cycle itself. The cycle is over (node, instruction
+/// index) states, so its period is a multiple of the number of instructions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Walk {
+    pub prefix: u64,
+    pub period: u64,
+    /// Steps before the cycle at which the ghost stands on an end node
+    pub early: Vec<u64>,
+    /// Steps within the first lap of the cycle at which the ghost stands on
+    /// an end node. It will be on one again every `period` steps after them
+    pub offsets: Vec<u64>,
+}
+
+impl Walk {
+    pub fn is_end(&self, step: u64) -> bool {
+        if step < self.prefix {
+            self.early.contains(&step)
+        } else {
+            step > 0 && self.offsets.iter().any(|offset| offset % self.period == step % self.period)
+        }
+    }
+
+    pub fn first_end(&self) -> Option<u64> {
+        self.early.first().or(self.offsets.first()).copied()
+    }
+}
+
+// Merges x = a1 (mod m1) and x = a2 (mod m2) into a single congruence, if
+// they have a common solution
+fn merge((a1, m1): (i128, i128), (a2, m2): (i128, i128)) -> Option<(i128, i128)> {
+    let egcd = m1.extended_gcd(&m2);
+    let g = egcd.gcd;
+    if (a2 - a1) % g != 0 {
+        return None
+    }
+    let lcm = (m1 / g).checked_mul(m2)?;
+    // egcd.x * m1 = g (mod m2), so it's the inverse of m1 / g modulo m2 / g
+    let k = ((a2 - a1) / g).checked_mul(egcd.x)?.mod_floor(&(m2 / g));
+    let x = m1.checked_mul(k)?.checked_add(a1)?.mod_floor(&lcm);
+
+    Some((x, lcm))
+}
+
+/// Finds the first step at which every ghost stands on an end node at the
+/// same time. Returns `None` if that never happens (or if the answer doesn't
+/// fit in a `u64`)
+pub fn coincide(walks: &[Walk]) -> Option<u64> {
+    let longest_prefix = walks.iter().map(|walk| walk.prefix).max()?;
+
+    // Before the longest prefix, it can only happen at one of the early steps
+    // of that ghost
+    let early = walks.iter()
+        .flat_map(|walk| walk.early.iter().copied())
+        .filter(|&step| walks.iter().all(|walk| walk.is_end(step)))
+        .min();
+
+    // After it, all of the ghosts are cycling. Solve for every combination
+    // of offsets
+    let mut congruences = vec![(0i128, 1i128)];
+    for walk in walks {
+        let period = walk.period as i128;
+        let mut merged = congruences.iter()
+            .flat_map(|&congruence| {
+                walk.offsets.iter()
+                    .filter_map(move |&offset| merge(congruence, (offset as i128 % period, period)))
+            })
+            .collect::<Vec<_>>();
+        merged.sort();
+        merged.dedup();
+        congruences = merged;
+    }
+    let lowest = longest_prefix.max(1) as i128;
+    let cycling = congruences.into_iter()
+        .filter_map(|(x, modulus)| {
+            let laps = Integer::div_ceil(&(lowest - x).max(0), &modulus);
+            u64::try_from(x + laps.checked_mul(modulus)?).ok()
+        })
+        .min();
+
+    match (early, cycling) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{coincide, Walk};
+
+    fn walk(prefix: u64, period: u64, early: &[u64], offsets: &[u64]) -> Walk {
+        Walk { prefix, period, early: early.to_vec(), offsets: offsets.to_vec() }
+    }
+
+    fn brute_force(walks: &[Walk], limit: u64) -> Option<u64> {
+        (1..limit).find(|&step| walks.iter().all(|walk| walk.is_end(step)))
+    }
+
+    #[test]
+    fn clean_cycles() {
+        // What the LCM of the first steps assumes
+        let walks = [walk(2, 2, &[], &[2]), walk(3, 3, &[], &[3])];
+        assert_eq!(coincide(&walks), Some(6));
+    }
+
+    #[test]
+    fn offsets_and_prefixes() {
+        let walks = [walk(3, 4, &[1], &[5]), walk(0, 6, &[], &[3])];
+        assert_eq!(coincide(&walks), Some(9));
+
+        // Only the early steps match
+        let walks = [walk(5, 2, &[4], &[5]), walk(0, 4, &[], &[4])];
+        assert_eq!(coincide(&walks), Some(4));
+
+        // Periods 4 and 6 with offsets of different parity never agree
+        let walks = [walk(0, 4, &[], &[2]), walk(0, 6, &[], &[3])];
+        assert_eq!(coincide(&walks), None);
+        assert_eq!(coincide(&[]), None);
+    }
+
+    #[test]
+    fn against_brute_force() {
+        let mut seed = 42u64;
+        let mut next = |modulo: u64| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) % modulo
+        };
+
+        for _ in 0..500 {
+            let walks = (0..1 + next(3))
+                .map(|_| {
+                    let prefix = next(6);
+                    let period = 1 + next(8);
+                    let early = (1..prefix).filter(|_| next(3) == 0).collect::<Vec<_>>();
+                    let offsets = (prefix.max(1)..prefix + period).filter(|_| next(3) == 0).collect::<Vec<_>>();
+                    walk(prefix, period, &early, &offsets)
+                })
+                .collect::<Vec<_>>();
+
+            // Past the longest prefix plus the product of the periods, everything repeats
+            let limit = 8 + walks.iter().map(|walk| walk.period).product::<u64>();
+            assert_eq!(coincide(&walks), brute_force(&walks, limit), "{walks:?}");
+        }
+    }
+}
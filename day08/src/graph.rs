@@ -0,0 +1,217 @@
+use std::fmt::Write;
+
+use crate::{NodeSet, Problem};
+
+const PALETTE: [&str; 6] = ["blue", "darkorange", "purple", "darkgreen", "brown", "deeppink"];
+
+// Node names can start with digits, which DOT only accepts in quoted IDs
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl Problem {
+    /// The strongly connected components of the network, following both
+    /// left and right edges, in reverse topological order. Uses an iterative
+    /// version of Tarjan's algorithm, so that large networks don't overflow
+    /// the stack
+    pub fn components(&self) -> Vec<Vec<u32>> {
+        let size = self.len();
+        let mut index = vec![u32::MAX; size];
+        let mut low = vec![0; size];
+        let mut on_stack = NodeSet::new(size);
+        let mut stack = vec![];
+        let mut components = vec![];
+        let mut counter = 0;
+
+        for root in 0..size as u32 {
+            if index[root as usize] != u32::MAX {
+                continue
+            }
+
+            // Each frame holds a node and the next of its edges to explore
+            let mut calls = vec![(root, 0)];
+            index[root as usize] = counter;
+            low[root as usize] = counter;
+            counter += 1;
+            stack.push(root);
+            on_stack.insert(root);
+
+            while let Some((node, edge)) = calls.last_mut() {
+                let node = *node;
+                if *edge < 2 {
+                    let next = self.edges[node as usize][*edge];
+                    *edge += 1;
+                    if index[next as usize] == u32::MAX {
+                        index[next as usize] = counter;
+                        low[next as usize] = counter;
+                        counter += 1;
+                        stack.push(next);
+                        on_stack.insert(next);
+                        calls.push((next, 0));
+                    } else if on_stack.contains(next) {
+                        low[node as usize] = low[node as usize].min(index[next as usize]);
+                    }
+                    continue
+                }
+
+                calls.pop();
+                if let Some(&(parent, _)) = calls.last() {
+                    low[parent as usize] = low[parent as usize].min(low[node as usize]);
+                }
+                if low[node as usize] == index[node as usize] {
+                    let mut component = vec![];
+                    while let Some(member) = stack.pop() {
+                        on_stack.remove(member);
+                        component.push(member);
+                        if member == node {
+                            break
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+
+        components
+    }
+
+    /// Every node that can be reached from `start` following any edges,
+    /// regardless of the instructions
+    pub fn reachable(&self, start: u32) -> NodeSet {
+        let mut seen = NodeSet::new(self.len());
+        let mut pending = vec![start];
+        seen.insert(start);
+
+        while let Some(node) = pending.pop() {
+            for next in self.edges[node as usize] {
+                if seen.insert(next) {
+                    pending.push(next);
+                }
+            }
+        }
+
+        seen
+    }
+
+    /// The nodes a ghost starting at `start` goes through once it's in its
+    /// cycle, in order. Nodes can appear more than once, as the cycle is over
+    /// (node, instruction index) states
+    pub fn cycle(&self, start: u32) -> Vec<u32> {
+        let walk = self.walk_ids(start, &NodeSet::new(self.len()));
+        let mut state = (start, 0);
+        for _ in 0..walk.prefix {
+            state = self.step(state);
+        }
+
+        (0..walk.period)
+            .map(|_| {
+                let node = state.0;
+                state = self.step(state);
+                node
+            })
+            .collect()
+    }
+
+    /// Renders the network in Graphviz format. Nodes ending in `start` and
+    /// `end` are filled in, and the edges that make up the cycle of each
+    /// ghost get a colour of their own
+    pub fn to_dot(&self, start: char, end: char) -> String {
+        let mut cycle_edges = vec![[None; 2]; self.len()];
+        for (ghost, id) in self.node_set(|s| s.ends_with(start)).iter().enumerate() {
+            let walk = self.walk_ids(id, &NodeSet::new(self.len()));
+            let mut state = (id, 0);
+            for _ in 0..walk.prefix {
+                state = self.step(state);
+            }
+            for _ in 0..walk.period {
+                let (node, idx) = state;
+                let direction = self.instructions[idx].index();
+                cycle_edges[node as usize][direction].get_or_insert(PALETTE[ghost % PALETTE.len()]);
+                state = self.step(state);
+            }
+        }
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph {{").unwrap();
+        for (id, name) in self.names.iter().enumerate() {
+            let node = quote(name);
+            if name.ends_with(start) {
+                writeln!(dot, "\t{node} [style=filled, fillcolor=palegreen];").unwrap();
+            } else if name.ends_with(end) {
+                writeln!(dot, "\t{node} [style=filled, fillcolor=lightcoral];").unwrap();
+            }
+            for (direction, label) in ["L", "R"].into_iter().enumerate() {
+                let target = quote(self.name(self.edges[id][direction]));
+                match cycle_edges[id][direction] {
+                    Some(colour) => writeln!(dot, "\t{node} -> {target} [label={label}, color={colour}, penwidth=2];").unwrap(),
+                    None => writeln!(dot, "\t{node} -> {target} [label={label}];").unwrap(),
+                }
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_instructions;
+
+    const SAMPLE: &[u8] = include_bytes!("../input.small");
+    const SAMPLE_REPEAT: &[u8] = include_bytes!("../input.small.2");
+    const SAMPLE_GHOSTS: &[u8] = include_bytes!("../input.small.3");
+
+    #[test]
+    fn components() {
+        let problem = parse_instructions(SAMPLE).unwrap();
+        let components = problem.components();
+        assert_eq!(components.len(), 7);
+        // Reverse topological order: AAA leads everywhere, so it comes last
+        assert_eq!(components.last(), Some(&vec![problem.id("AAA").unwrap()]));
+
+        let problem = parse_instructions(SAMPLE_REPEAT).unwrap();
+        let mut components = problem.components()
+            .into_iter()
+            .map(|component| {
+                let mut names = component.into_iter().map(|id| problem.name(id)).collect::<Vec<_>>();
+                names.sort();
+                names
+            })
+            .collect::<Vec<_>>();
+        components.sort();
+        assert_eq!(components, [vec!["AAA", "BBB"], vec!["ZZZ"]]);
+    }
+
+    #[test]
+    fn reachable_and_cycles() {
+        let problem = parse_instructions(SAMPLE_GHOSTS).unwrap();
+        let names = |ids: &[u32]| ids.iter().map(|&id| problem.name(id)).collect::<Vec<_>>();
+
+        let reachable = problem.reachable(problem.id("11A").unwrap()).iter().collect::<Vec<_>>();
+        assert_eq!(names(&reachable), ["11A", "11B", "11Z", "XXX"]);
+        let reachable = problem.reachable(problem.id("22A").unwrap()).iter().collect::<Vec<_>>();
+        assert_eq!(names(&reachable), ["22A", "22B", "22C", "22Z", "XXX"]);
+
+        assert_eq!(names(&problem.cycle(problem.id("11A").unwrap())), ["11B", "11Z"]);
+        assert_eq!(names(&problem.cycle(problem.id("22A").unwrap())), ["22B", "22C", "22Z", "22B", "22C", "22Z"]);
+    }
+
+    #[test]
+    fn dot() {
+        let problem = parse_instructions(SAMPLE_GHOSTS).unwrap();
+        let dot = problem.to_dot('A', 'Z');
+
+        assert!(dot.starts_with("digraph {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("\t\"11A\" [style=filled, fillcolor=palegreen];\n"));
+        assert!(dot.contains("\t\"22Z\" [style=filled, fillcolor=lightcoral];\n"));
+        assert!(dot.contains("\t\"11A\" -> \"11B\" [label=L];\n"));
+        assert!(dot.contains("\t\"11B\" -> \"11Z\" [label=R, color=blue, penwidth=2];\n"));
+        assert!(dot.contains("\t\"22C\" -> \"22Z\" [label=L, color=darkorange, penwidth=2];\n"));
+        assert!(dot.contains("\t\"22C\" -> \"22Z\" [label=R, color=darkorange, penwidth=2];\n"));
+        assert_eq!(dot.lines().filter(|line| line.contains("->")).count(), 16);
+
+        assert_eq!(super::quote(r#"a"b\c"#), r#""a\"b\\c""#);
+    }
+}
@@ -1,80 +1,425 @@
+pub mod ghost;
+pub mod graph;
+
+use std::fmt::Display;
 use std::io::BufRead;
 use std::collections::HashMap;
 
-#[derive(Debug)]
+use ghost::Walk;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The walk got back to a state it had already been in, without
+    /// reaching the end
+    Unreachable,
+    UnknownNode(String),
+    /// A line of the input that doesn't follow the format
+    Parse(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Unreachable => write!(f, "The end can't be reached"),
+            Error::UnknownNode(name) => write!(f, "Unknown node {name:?}"),
+            Error::Parse(line) => write!(f, "Can't parse {line:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone, Copy)]
 enum Instruction {
     Left,
     Right,
 }
 
-#[derive(Debug)]
-pub struct Node {
-    left: String,
-    right: String,
+impl Instruction {
+    fn index(self) -> usize {
+        match self {
+            Instruction::Left => 0,
+            Instruction::Right => 1,
+        }
+    }
+}
+
+/// A set of nodes, stored as one bit per node ID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeSet {
+    bits: Vec<u64>,
+}
+
+impl NodeSet {
+    pub fn new(size: usize) -> Self {
+        NodeSet { bits: vec![0; size.div_ceil(64)] }
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        self.bits[id as usize / 64] & (1 << (id % 64)) != 0
+    }
+
+    /// Adds a node, returning whether it wasn't there already
+    pub fn insert(&mut self, id: u32) -> bool {
+        let was_there = self.contains(id);
+        self.bits[id as usize / 64] |= 1 << (id % 64);
+
+        !was_there
+    }
+
+    /// Takes a node out, returning whether it was there
+    pub fn remove(&mut self, id: u32) -> bool {
+        let was_there = self.contains(id);
+        self.bits[id as usize / 64] &= !(1 << (id % 64));
+
+        was_there
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.bits.iter()
+            .enumerate()
+            .flat_map(|(idx, &word)| {
+                (0..64).filter(move |bit| word & (1 << bit) != 0)
+                    .map(move |bit| (idx * 64 + bit) as u32)
+            })
+    }
 }
 
+/// The network, compiled down to integer node IDs. Names are interned in
+/// the order they're defined in the input, and `edges[id]` holds the left
+/// and right targets of each node
 #[derive(Debug)]
 pub struct Problem {
     instructions: Vec<Instruction>,
-    nodes: HashMap<String, Node>
+    names: Vec<String>,
+    ids: HashMap<String, u32>,
+    edges: Vec<[u32; 2]>,
 }
 
 impl Problem {
-    pub fn iterate(&self, start: &str, end_p: impl Fn(&str) -> bool) -> u64 {
+    pub fn id(&self, name: &str) -> Result<u32, Error> {
+        self.ids.get(name).copied().ok_or_else(|| Error::UnknownNode(name.to_string()))
+    }
+
+    pub fn name(&self, id: u32) -> &str {
+        &self.names[id as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The nodes whose names satisfy the predicate
+    pub fn node_set(&self, pred: impl Fn(&str) -> bool) -> NodeSet {
+        let mut set = NodeSet::new(self.len());
+        for (id, name) in self.names.iter().enumerate() {
+            if pred(name) {
+                set.insert(id as u32);
+            }
+        }
+
+        set
+    }
+
+    // The state after one step from `node`, with the instruction at `idx`
+    fn step(&self, (node, idx): (u32, usize)) -> (u32, usize) {
+        let next = self.edges[node as usize][self.instructions[idx].index()];
+
+        (next, (idx + 1) % self.instructions.len())
+    }
+
+    /// Counts the steps from `start` to the first node that satisfies
+    /// `end_p`. Gives up with `Error::Unreachable` once the walk repeats
+    /// itself
+    pub fn iterate(&self, start: &str, end_p: impl Fn(&str) -> bool) -> Result<u64, Error> {
+        self.iterate_ids(self.id(start)?, &self.node_set(end_p))
+    }
+
+    /// Same as `iterate`, for node IDs
+    pub fn iterate_ids(&self, start: u32, end: &NodeSet) -> Result<u64, Error> {
         let mut steps = 0;
-        let mut current_node = self.nodes.get(start).unwrap();
+        let mut current = start;
+        // Every cycle of (node, instruction index) states goes through index
+        // 0, so it's enough to remember where each pass over the
+        // instructions started
+        let mut visited = NodeSet::new(self.len());
 
         loop {
+            if !visited.insert(current) {
+                return Err(Error::Unreachable)
+            }
+
             for inst in self.instructions.iter() {
                 steps += 1;
+                current = self.edges[current as usize][inst.index()];
 
-                let next_node = match inst {
-                    Instruction::Left => current_node.left.as_str(),
-                    Instruction::Right => current_node.right.as_str(),
-                };
-
-                if end_p(next_node) {
-                    return steps
+                if end.contains(current) {
+                    return Ok(steps)
                 }
-
-                current_node = self.nodes.get(next_node).unwrap();
             }
         }
     }
 
-    pub fn parallel_iterate(&self, start: char, end: char) -> u64 {
-        let starting_keys = self.nodes.keys()
-            .filter(|&s| s.ends_with(start))
-            .map(|s| s.as_str());
+    /// Follows a ghost from `start` until it repeats a state, recording the
+    /// steps at which it stands on an end node
+    pub fn walk(&self, start: &str, end_p: impl Fn(&str) -> bool) -> Result<Walk, Error> {
+        Ok(self.walk_ids(self.id(start)?, &self.node_set(end_p)))
+    }
+
+    /// Same as `walk`, for node IDs. Uses Brent's algorithm to find the
+    /// cycle, so it doesn't need to remember the states it has been through
+    pub fn walk_ids(&self, start: u32, end: &NodeSet) -> Walk {
+        let origin = (start, 0);
+
+        let mut power = 1;
+        let mut period = 1;
+        let mut tortoise = origin;
+        let mut hare = self.step(origin);
+        while tortoise != hare {
+            if power == period {
+                tortoise = hare;
+                power *= 2;
+                period = 0;
+            }
+            hare = self.step(hare);
+            period += 1;
+        }
+
+        let mut tortoise = origin;
+        let mut hare = origin;
+        for _ in 0..period {
+            hare = self.step(hare);
+        }
+        let mut prefix = 0;
+        while tortoise != hare {
+            tortoise = self.step(tortoise);
+            hare = self.step(hare);
+            prefix += 1;
+        }
+
+        // Step 0 doesn't count as a hit, but a lap later it does. Otherwise,
+        // the last step is a repetition of the first one in the cycle
+        let last = if prefix == 0 { period } else { prefix + period - 1 };
+        let mut hits = vec![];
+        let mut state = origin;
+        for steps in 1..=last {
+            state = self.step(state);
+            if end.contains(state.0) {
+                hits.push(steps);
+            }
+        }
+        let (early, offsets) = hits.into_iter().partition(|&step| step < prefix);
+
+        Walk { prefix, period, early, offsets }
+    }
 
-        // Now, find the path length for each ghost
-        let lengths = starting_keys.map(|key| self.iterate(key, |s| s.ends_with(end)));
+    /// Finds the first step at which all the ghosts, starting from every
+    /// node ending in `start`, stand on nodes ending in `end`. Returns `None`
+    /// if that never happens
+    pub fn parallel_iterate(&self, start: char, end: char) -> Option<u64> {
+        let end = self.node_set(|s| s.ends_with(end));
+        let walks = self.node_set(|s| s.ends_with(start))
+            .iter()
+            .map(|id| self.walk_ids(id, &end))
+            .collect::<Vec<_>>();
 
-        // And find the LCM for the lenghts, that's our answer
-        lengths.reduce(|a, b| num::integer::lcm(a, b)).unwrap()
+        ghost::coincide(&walks)
     }
 }
 
-pub fn parse_instructions<R: BufRead>(stream: R) -> Problem {
+/// Reads the instructions and the network. Every node named as a target has
+/// to be defined as well
+pub fn parse_instructions<R: BufRead>(stream: R) -> Result<Problem, Error> {
     let mut lines = stream.lines();
 
-    let instructions: Vec<Instruction> = lines.next().unwrap().expect("Something horrible happened")
+    let first = lines.next().map(|line| line.expect("Something horrible happened")).unwrap_or_default();
+    let instructions = first
         .chars()
-        .map(|c| if c == 'L' { Instruction::Left } else { Instruction::Right })
-        .collect();
+        .map(|c| match c {
+            'L' => Ok(Instruction::Left),
+            'R' => Ok(Instruction::Right),
+            _ => Err(Error::Parse(first.clone())),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if instructions.is_empty() {
+        return Err(Error::Parse(first))
+    }
 
     let _ = lines.next(); // Skip
 
-    let nodes: HashMap<_, _> = HashMap::from_iter(lines.into_iter().map(|line| {
-            let line = line.expect("Something horrible happened");
-            let (node, edges) = line.split_once(" = ").unwrap();
-            let (left, right) = edges[1..edges.len() - 1].split_once(", ").unwrap();
+    let mut names = vec![];
+    let mut ids = HashMap::new();
+    let mut targets = vec![];
+    for line in lines {
+        let line = line.expect("Something horrible happened");
+        let Some((node, edges)) = line.split_once(" = ") else {
+            return Err(Error::Parse(line))
+        };
+        let Some((left, right)) = edges.strip_prefix('(')
+            .and_then(|edges| edges.strip_suffix(')'))
+            .and_then(|edges| edges.split_once(", ")) else {
+            return Err(Error::Parse(line))
+        };
+        let Ok(id) = u32::try_from(names.len()) else {
+            return Err(Error::Parse(line))
+        };
+        if ids.insert(node.to_string(), id).is_some() {
+            return Err(Error::Parse(line))
+        }
+        names.push(node.to_string());
+        targets.push([left.to_string(), right.to_string()]);
+    }
 
-            (node.to_string(), Node { left: left.to_string(), right: right.to_string() })
-        }));
+    let edges = targets.into_iter()
+        .map(|[left, right]| {
+            let id = |name: String| ids.get(&name).copied().ok_or(Error::UnknownNode(name));
+            Ok([id(left)?, id(right)?])
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
 
-    Problem {
+    Ok(Problem {
         instructions,
-        nodes,
+        names,
+        ids,
+        edges,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ghost::Walk;
+    use std::collections::HashMap;
+
+    use crate::{parse_instructions, Error, NodeSet};
+
+    const SAMPLE: &[u8] = include_bytes!("../input.small");
+    const SAMPLE_REPEAT: &[u8] = include_bytes!("../input.small.2");
+    const SAMPLE_GHOSTS: &[u8] = include_bytes!("../input.small.3");
+
+    #[test]
+    fn ghost_walks() {
+        let problem = parse_instructions(SAMPLE_GHOSTS).unwrap();
+
+        assert_eq!(problem.walk("11A", |s| s.ends_with('Z')).unwrap(),
+                   Walk { prefix: 1, period: 2, early: vec![], offsets: vec![2] });
+        assert_eq!(problem.walk("22A", |s| s.ends_with('Z')).unwrap(),
+                   Walk { prefix: 1, period: 6, early: vec![], offsets: vec![3, 6] });
+        assert_eq!(problem.parallel_iterate('A', 'Z'), Some(6));
+    }
+
+    #[test]
+    fn uneven_ghosts() {
+        // The first ghost stays at the end after 2 steps, the second one is
+        // there every other step. The LCM of the first steps would say 2
+        let input = "L\n\n1A = (1B, 1B)\n1B = (1Z, 1Z)\n1Z = (1Z, 1Z)\n2A = (2Z, 2Z)\n2Z = (2B, 2B)\n2B = (2Z, 2Z)\n";
+        let problem = parse_instructions(input.as_bytes()).unwrap();
+        assert_eq!(problem.parallel_iterate('A', 'Z'), Some(3));
+
+        // These ones are never there at the same time
+        let input = "L\n\n1A = (1Z, 1Z)\n1Z = (1B, 1B)\n1B = (1Z, 1Z)\n2A = (2B, 2B)\n2B = (2Z, 2Z)\n2Z = (2B, 2B)\n";
+        let problem = parse_instructions(input.as_bytes()).unwrap();
+        assert_eq!(problem.parallel_iterate('A', 'Z'), None);
+    }
+
+    #[test]
+    fn iterate() {
+        let problem = parse_instructions(SAMPLE).unwrap();
+        assert_eq!(problem.iterate("AAA", |s| s == "ZZZ"), Ok(2));
+        // From DDD there's nowhere else to go
+        assert_eq!(problem.iterate("AAA", |s| s == "FOO"), Err(Error::Unreachable));
+        assert_eq!(problem.iterate("DDD", |s| s == "ZZZ"), Err(Error::Unreachable));
+        assert_eq!(problem.iterate("FOO", |s| s == "ZZZ"), Err(Error::UnknownNode("FOO".to_string())));
+
+        let problem = parse_instructions(SAMPLE_REPEAT).unwrap();
+        assert_eq!(problem.iterate("AAA", |s| s == "ZZZ"), Ok(6));
+        // Goes around AAA and BBB for a while, and then stays at ZZZ
+        assert_eq!(problem.iterate("AAA", |s| s == "CCC"), Err(Error::Unreachable));
+    }
+
+    #[test]
+    fn validation() {
+        let missing = "LR\n\nAAA = (BBB, ZZZ)\nBBB = (AAA, AAA)\n";
+        assert_eq!(parse_instructions(missing.as_bytes()).err(), Some(Error::UnknownNode("ZZZ".to_string())));
+
+        let bad_line = "LR\n\nAAA = BBB, AAA\nBBB = (AAA, AAA)\n";
+        assert_eq!(parse_instructions(bad_line.as_bytes()).err(), Some(Error::Parse("AAA = BBB, AAA".to_string())));
+
+        for instructions in ["LRX", ""] {
+            let input = format!("{instructions}\n\nAAA = (AAA, AAA)\n");
+            assert_eq!(parse_instructions(input.as_bytes()).err(), Some(Error::Parse(instructions.to_string())));
+        }
+    }
+
+    #[test]
+    fn interned_ids() {
+        let problem = parse_instructions(SAMPLE).unwrap();
+        assert_eq!(problem.len(), 7);
+        assert_eq!(problem.id("AAA"), Ok(0));
+        assert_eq!(problem.id("ZZZ"), Ok(6));
+        assert_eq!(problem.name(2), "CCC");
+        assert_eq!(problem.id("FOO"), Err(Error::UnknownNode("FOO".to_string())));
+
+        let ends = problem.node_set(|s| s.starts_with(['D', 'Z']));
+        assert_eq!(ends.iter().collect::<Vec<_>>(), [3, 6]);
+        assert_eq!(problem.iterate_ids(0, &ends), Ok(2));
+
+        let mut set = NodeSet::new(130);
+        assert!(set.insert(129));
+        assert!(!set.insert(129));
+        assert!(set.insert(64));
+        assert!(!set.contains(0));
+        assert_eq!(set.iter().collect::<Vec<_>>(), [64, 129]);
+
+        let duplicated = "L\n\nAAA = (AAA, AAA)\nAAA = (AAA, AAA)\n";
+        assert_eq!(parse_instructions(duplicated.as_bytes()).err(), Some(Error::Parse("AAA = (AAA, AAA)".to_string())));
+    }
+
+    #[test]
+    fn random_networks() {
+        let mut seed = 7u64;
+        let mut next = |modulo: usize| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as usize % modulo
+        };
+
+        for _ in 0..200 {
+            let size = 2 + next(12);
+            let names = (0..size).map(|n| format!("{n:02}{}", ['A', 'B', 'Z'][next(3)])).collect::<Vec<_>>();
+            let instructions = (0..1 + next(4)).map(|_| ['L', 'R'][next(2)]).collect::<String>();
+            let mut input = format!("{instructions}\n\n");
+            let mut edges = HashMap::new();
+            for name in &names {
+                let (left, right) = (&names[next(size)], &names[next(size)]);
+                input.push_str(&format!("{name} = ({left}, {right})\n"));
+                edges.insert(name.as_str(), (left.as_str(), right.as_str()));
+            }
+            let problem = parse_instructions(input.as_bytes()).unwrap();
+
+            let first = problem.iterate(&names[0], |s| s.ends_with('Z'));
+            let walk = problem.walk(&names[0], |s| s.ends_with('Z')).unwrap();
+            assert_eq!(first.ok(), walk.first_end(), "{input}");
+
+            // Move all the ghosts together, for long enough to see every
+            // combination of states
+            let mut ghosts = names.iter().filter(|name| name.ends_with('A')).map(|name| name.as_str()).collect::<Vec<_>>();
+            if ghosts.is_empty() || ghosts.len() > 3 {
+                continue
+            }
+            let limit = (size * instructions.len()).pow(ghosts.len() as u32) as u64;
+            let expected = (1..=limit).zip(instructions.chars().cycle())
+                .find(|&(_, inst)| {
+                    for ghost in ghosts.iter_mut() {
+                        let (left, right) = edges[ghost];
+                        *ghost = if inst == 'L' { left } else { right };
+                    }
+                    ghosts.iter().all(|ghost| ghost.ends_with('Z'))
+                })
+                .map(|(steps, _)| steps);
+            assert_eq!(problem.parallel_iterate('A', 'Z'), expected, "{input}");
+        }
     }
 }
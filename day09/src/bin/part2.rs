@@ -1,13 +1,18 @@
 use std::io::stdin;
+use std::process::exit;
 
-use day09::find_prev;
+use day09::extrapolator::Extrapolator;
 
 fn main() {
-    let result: i64 = stdin().lines().map(|line| {
-        let values = line.unwrap().split_whitespace()
-            .map(|k| k.parse::<i64>().unwrap())
-            .collect::<Vec<_>>();
-        find_prev(&values)
+    let result: i64 = stdin().lines().enumerate().map(|(n, line)| {
+        let mut extrapolator = Extrapolator::new();
+        line.unwrap().split_whitespace()
+            .try_for_each(|k| extrapolator.push(k.parse::<i64>().unwrap()))
+            .and_then(|_| extrapolator.prev())
+            .unwrap_or_else(|err| {
+                eprintln!("Line {}: {err}", n + 1);
+                exit(1);
+            })
     }).sum();
 
     println!("Sum of predecessors: {result}");
@@ -0,0 +1,173 @@
+use crate::Error;
+
+/// Extrapolates a series as its values come in, without storing them. Keeps
+/// the last value of each row of the difference table (and the first one,
+/// to extrapolate backwards), leaving out the trailing zeros. For a
+/// polynomial series, each update takes time proportional to its degree
+#[derive(Debug, Clone, Default)]
+pub struct Extrapolator {
+    last: Vec<i128>,
+    first: Vec<i128>,
+    count: usize,
+    max_degree: Option<usize>,
+}
+
+impl Extrapolator {
+    pub fn new() -> Self {
+        Extrapolator::default()
+    }
+
+    /// An extrapolator that rejects any value taking the degree of the
+    /// series over `max_degree`
+    pub fn with_max_degree(max_degree: usize) -> Self {
+        Extrapolator { max_degree: Some(max_degree), ..Extrapolator::default() }
+    }
+
+    /// Adds the next value of the series. On error, the extrapolator is left
+    /// as it was
+    pub fn push(&mut self, value: i64) -> Result<(), Error> {
+        let mut last = Vec::with_capacity(self.last.len() + 1);
+        let mut top = value as i128;
+        for &previous in self.last.iter() {
+            last.push(top);
+            top = top.checked_sub(previous).ok_or(Error::Overflow)?;
+        }
+
+        // Every difference past the explicit ones was zero, so the new ones
+        // are all equal to the last difference computed
+        let mut first = None;
+        if top != 0 {
+            let filled = self.count + 1 - self.last.len();
+            if self.max_degree.is_some_and(|max| last.len() + filled > max + 1) {
+                return Err(Error::NotPolynomial)
+            }
+            last.extend(std::iter::repeat_n(top, filled));
+            first = Some(top);
+        }
+        while last.last() == Some(&0) {
+            last.pop();
+        }
+
+        if let Some(top) = first {
+            self.first.resize(self.count, 0);
+            self.first.push(top);
+        }
+        self.last = last;
+        self.count += 1;
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The degree of the series, once a row of zeros confirms it
+    pub fn degree(&self) -> Result<usize, Error> {
+        if self.count == 0 {
+            Err(Error::Empty)
+        } else if self.count < 2 || self.last.len() >= self.count {
+            Err(Error::NotPolynomial)
+        } else {
+            Ok(self.last.len().saturating_sub(1))
+        }
+    }
+
+    /// The value that would come after the last one
+    pub fn next(&self) -> Result<i64, Error> {
+        self.degree()?;
+        let next = self.last.iter()
+            .try_fold(0i128, |acc, &k| acc.checked_add(k))
+            .ok_or(Error::Overflow)?;
+
+        i64::try_from(next).map_err(|_| Error::Overflow)
+    }
+
+    /// The value that would come before the first one
+    pub fn prev(&self) -> Result<i64, Error> {
+        self.degree()?;
+        let prev = self.first.iter()
+            .enumerate()
+            .try_fold(0i128, |acc, (j, &k)| if j % 2 == 0 { acc.checked_add(k) } else { acc.checked_sub(k) })
+            .ok_or(Error::Overflow)?;
+
+        i64::try_from(prev).map_err(|_| Error::Overflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Extrapolator;
+    use crate::{find_next, find_prev, Error};
+
+    fn feed(series: &[i64]) -> Extrapolator {
+        let mut extrapolator = Extrapolator::new();
+        for &value in series {
+            extrapolator.push(value).unwrap();
+        }
+        extrapolator
+    }
+
+    #[test]
+    fn sample() {
+        let extrapolator = feed(&[10, 13, 16, 21, 30, 45]);
+        assert_eq!(extrapolator.degree(), Ok(3));
+        assert_eq!(extrapolator.next(), Ok(68));
+        assert_eq!(extrapolator.prev(), Ok(5));
+
+        assert_eq!(Extrapolator::new().next(), Err(Error::Empty));
+        assert_eq!(feed(&[0]).prev(), Err(Error::NotPolynomial));
+        assert_eq!(feed(&[0, 0]).next(), Ok(0));
+    }
+
+    #[test]
+    fn same_as_batch() {
+        let mut seed = 1u64;
+        let mut next = |modulo: u64| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((seed >> 33) % modulo) as i64
+        };
+
+        for _ in 0..500 {
+            // Polynomials of low degree, some of them followed by noise
+            let coefficients = (0..1 + next(5)).map(|_| next(21) - 10).collect::<Vec<_>>();
+            let mut series = (0..next(12))
+                .map(|x| coefficients.iter().rev().fold(0, |acc, &c| acc * x + c))
+                .collect::<Vec<_>>();
+            if next(3) == 0 {
+                series.extend((0..next(3)).map(|_| next(100)));
+            }
+
+            let mut extrapolator = Extrapolator::new();
+            for (n, &value) in series.iter().enumerate() {
+                extrapolator.push(value).unwrap();
+                assert_eq!(extrapolator.next(), find_next(&series[..=n]), "{:?}", &series[..=n]);
+                assert_eq!(extrapolator.prev(), find_prev(&series[..=n]), "{:?}", &series[..=n]);
+            }
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let mut extrapolator = Extrapolator::with_max_degree(1);
+        for value in [1, 3, 5] {
+            extrapolator.push(value).unwrap();
+        }
+        assert_eq!(extrapolator.push(8), Err(Error::NotPolynomial));
+        // The rejected value doesn't count
+        assert_eq!(extrapolator.next(), Ok(7));
+        assert_eq!(extrapolator.len(), 3);
+
+        let mut extrapolator = Extrapolator::new();
+        let mut result = Ok(());
+        for n in 0..80 {
+            result = result.and(extrapolator.push(if n % 2 == 0 { i64::MAX } else { i64::MIN }));
+        }
+        assert_eq!(result, Err(Error::Overflow));
+        assert_eq!(feed(&[i64::MAX - 2, i64::MAX - 1, i64::MAX]).next(), Err(Error::Overflow));
+    }
+}
@@ -1,27 +1,54 @@
-pub fn find_next(series: &Vec<i64>) -> i64 {
-    let length = series.len();
-    let next_row: Vec<i64> = series[..length-1].iter().zip(series[1..].iter())
-        .map(|(&a, &b)| b - a)
-        .collect();
-
-    let last = *series.last().unwrap();
-    if !next_row.iter().all(|&k| k == 0) {
-        last + find_next(&next_row)
-    } else {
-        last
+pub mod extrapolator;
+pub mod polynomial;
+
+use std::fmt::Display;
+
+use polynomial::Polynomial;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    Empty,
+    /// The differences never got down to a row of zeros, or not within the
+    /// maximum degree allowed
+    NotPolynomial,
+    Overflow,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Empty => write!(f, "The series is empty"),
+            Error::NotPolynomial => write!(f, "The series doesn't follow a polynomial"),
+            Error::Overflow => write!(f, "The values are too large"),
+        }
     }
 }
 
-pub fn find_prev(series: &Vec<i64>) -> i64 {
-    let length = series.len();
-    let next_row: Vec<i64> = series[..length-1].iter().zip(series[1..].iter())
-        .map(|(&a, &b)| b - a)
-        .collect();
-
-    let first = *series.first().unwrap();
-    if !next_row.iter().all(|&k| k == 0) {
-        first - find_prev(&next_row)
-    } else {
-        first
+impl std::error::Error for Error {}
+
+pub fn find_next(series: &[i64]) -> Result<i64, Error> {
+    let next = Polynomial::fit(series)?.value_at(series.len() as i64)?;
+
+    i64::try_from(next).map_err(|_| Error::Overflow)
+}
+
+pub fn find_prev(series: &[i64]) -> Result<i64, Error> {
+    let prev = Polynomial::fit(series)?.value_at(-1)?;
+
+    i64::try_from(prev).map_err(|_| Error::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{find_next, find_prev, Error};
+
+    #[test]
+    fn next_and_prev() {
+        assert_eq!(find_next(&[10, 13, 16, 21, 30, 45]), Ok(68));
+        assert_eq!(find_prev(&[10, 13, 16, 21, 30, 45]), Ok(5));
+        assert_eq!(find_next(&[]), Err(Error::Empty));
+        assert_eq!(find_prev(&[7]), Err(Error::NotPolynomial));
+        assert_eq!(find_next(&[i64::MAX - 2, i64::MAX - 1, i64::MAX]), Err(Error::Overflow));
+        assert_eq!(find_prev(&[i64::MIN + 2, i64::MIN + 1, i64::MIN]), Ok(i64::MIN + 3));
     }
 }
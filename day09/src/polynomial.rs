@@ -0,0 +1,197 @@
+use num::{rational::Ratio, CheckedAdd};
+
+use crate::Error;
+
+/// The polynomial going through the values of a series, taking the first
+/// value as index 0. It's kept in Newton form: the first value of each row of
+/// the difference table, down to the last row that isn't all zeros
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polynomial {
+    differences: Vec<i128>,
+}
+
+impl Polynomial {
+    /// Fits a polynomial of any degree. The series needs at least one more
+    /// value than the degree plus one, so that the row of zeros confirming the
+    /// degree is actually seen
+    pub fn fit(series: &[i64]) -> Result<Self, Error> {
+        Polynomial::fit_with(series, None)
+    }
+
+    /// Same as `fit`, but fails as soon as the degree goes over `max_degree`
+    pub fn fit_with(series: &[i64], max_degree: Option<usize>) -> Result<Self, Error> {
+        if series.is_empty() {
+            return Err(Error::Empty)
+        }
+
+        let mut differences = vec![];
+        let mut row = series.iter().map(|&k| k as i128).collect::<Vec<_>>();
+
+        while let Some(&first) = row.first() {
+            if max_degree.is_some_and(|max| differences.len() > max) {
+                return Err(Error::NotPolynomial)
+            }
+            differences.push(first);
+            row = row.windows(2)
+                .map(|pair| pair[1].checked_sub(pair[0]).ok_or(Error::Overflow))
+                .collect::<Result<_, _>>()?;
+            if !row.is_empty() && row.iter().all(|&k| k == 0) {
+                return Ok(Polynomial { differences })
+            }
+        }
+
+        // Ran out of values before finding a row of zeros
+        Err(Error::NotPolynomial)
+    }
+
+    pub fn degree(&self) -> usize {
+        self.differences.len() - 1
+    }
+
+    /// The forward differences at index 0, from the value itself up to the
+    /// one of the highest order
+    pub fn differences(&self) -> &[i128] {
+        &self.differences
+    }
+
+    /// Coefficients in the usual form, starting with the constant term
+    pub fn coefficients(&self) -> Result<Vec<Ratio<i128>>, Error> {
+        let mut coefficients = vec![Ratio::from_integer(0); self.differences.len()];
+        // x (x - 1) ... (x - j + 1), which divided by j! is the binomial
+        // coefficient multiplying the j-th difference
+        let mut falling = vec![1i128];
+        let mut factorial = 1i128;
+
+        for (j, &difference) in self.differences.iter().enumerate() {
+            if j > 0 {
+                factorial = factorial.checked_mul(j as i128).ok_or(Error::Overflow)?;
+            }
+            for (coefficient, &term) in coefficients.iter_mut().zip(falling.iter()) {
+                let numerator = difference.checked_mul(term).ok_or(Error::Overflow)?;
+                *coefficient = coefficient.checked_add(&Ratio::new(numerator, factorial)).ok_or(Error::Overflow)?;
+            }
+
+            let mut next = vec![0; falling.len() + 1];
+            for (power, &term) in falling.iter().enumerate() {
+                next[power + 1] += term;
+                next[power] = term.checked_mul(j as i128)
+                    .and_then(|product| next[power].checked_sub(product))
+                    .ok_or(Error::Overflow)?;
+            }
+            falling = next;
+        }
+
+        Ok(coefficients)
+    }
+
+    /// Evaluates the polynomial at any index, before or after the series
+    pub fn value_at(&self, index: i64) -> Result<i128, Error> {
+        let index = index as i128;
+        let mut binomial = 1i128;
+        let mut total = 0i128;
+
+        for (j, &difference) in self.differences.iter().enumerate() {
+            if j > 0 {
+                // C(k, j) = C(k, j - 1) * (k - j + 1) / j, always exact
+                let j = j as i128;
+                binomial = binomial.checked_mul(index - j + 1).ok_or(Error::Overflow)? / j;
+            }
+            total = difference.checked_mul(binomial)
+                .and_then(|term| total.checked_add(term))
+                .ok_or(Error::Overflow)?;
+        }
+
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::rational::Ratio;
+
+    use super::Polynomial;
+    use crate::Error;
+
+    const SAMPLE_INPUT: &str = include_str!("../input.small");
+
+    fn sample() -> Vec<Vec<i64>> {
+        SAMPLE_INPUT.lines()
+            .map(|line| line.split_whitespace().map(|k| k.parse().unwrap()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn sample_polynomials() {
+        let fits = sample().iter().map(|series| Polynomial::fit(series).unwrap()).collect::<Vec<_>>();
+
+        assert_eq!(fits.iter().map(|fit| fit.degree()).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(fits[0].differences(), [0, 3]);
+        assert_eq!(fits[1].differences(), [1, 2, 1]);
+
+        // (x + 1)(x + 2) / 2
+        let half = |k| Ratio::new(k, 2);
+        assert_eq!(fits[1].coefficients().unwrap(), [half(2), half(3), half(1)]);
+        assert_eq!(fits[0].coefficients().unwrap(), [Ratio::from_integer(0), Ratio::from_integer(3)]);
+
+        assert_eq!(fits.iter().map(|fit| fit.value_at(6).unwrap()).collect::<Vec<_>>(), [18, 28, 68]);
+        assert_eq!(fits.iter().map(|fit| fit.value_at(-1).unwrap()).collect::<Vec<_>>(), [-3, 0, 5]);
+    }
+
+    #[test]
+    fn far_away() {
+        // 2x^3 - x + 7
+        let value = |x: i64| 2 * x.pow(3) - x + 7;
+        let series = (0..6).map(value).collect::<Vec<_>>();
+        let fit = Polynomial::fit(&series).unwrap();
+
+        assert_eq!(fit.degree(), 3);
+        assert_eq!(fit.coefficients().unwrap(), [7, -1, 0, 2].map(Ratio::from_integer));
+        for index in [-1000, -7, -1, 0, 5, 6, 100, 12345] {
+            assert_eq!(fit.value_at(index), Ok(value(index) as i128));
+        }
+    }
+
+    #[test]
+    fn coefficients_match_values() {
+        let quartic = (-3..5).map(|x: i64| x.pow(4) - 3 * x.pow(2) + 1).collect();
+        for series in sample().into_iter().chain([quartic, vec![0, 0, 0], vec![5, 5]]) {
+            let fit = Polynomial::fit(&series).unwrap();
+            let coefficients = fit.coefficients().unwrap();
+            for index in -5..15i64 {
+                let value = coefficients.iter()
+                    .rev()
+                    .fold(Ratio::from_integer(0), |acc, &c| acc * Ratio::from_integer(index as i128) + c);
+                assert_eq!(value, Ratio::from_integer(fit.value_at(index).unwrap()));
+            }
+            for (index, &value) in series.iter().enumerate() {
+                assert_eq!(fit.value_at(index as i64), Ok(value as i128));
+            }
+        }
+    }
+
+    #[test]
+    fn bad_series() {
+        assert_eq!(Polynomial::fit(&[]), Err(Error::Empty));
+        // A single value, or powers of two, never get to a row of zeros
+        assert_eq!(Polynomial::fit(&[5]), Err(Error::NotPolynomial));
+        assert_eq!(Polynomial::fit(&[1, 2, 4, 8, 16, 32]), Err(Error::NotPolynomial));
+        assert_eq!(Polynomial::fit(&[5, 5]).map(|fit| fit.degree()), Ok(0));
+
+        let squares = [0, 1, 4, 9, 16];
+        assert_eq!(Polynomial::fit_with(&squares, Some(2)).map(|fit| fit.degree()), Ok(2));
+        assert_eq!(Polynomial::fit_with(&squares, Some(1)), Err(Error::NotPolynomial));
+        assert_eq!(Polynomial::fit_with(&[1, 2, 4, 8, 16, 32, 64, 128], Some(3)), Err(Error::NotPolynomial));
+    }
+
+    #[test]
+    fn overflow() {
+        let swinging = (0..80).map(|n| if n % 2 == 0 { i64::MAX } else { i64::MIN }).collect::<Vec<_>>();
+        assert_eq!(Polynomial::fit(&swinging), Err(Error::Overflow));
+
+        let cubes = (0..5).map(|x: i64| x.pow(3)).collect::<Vec<_>>();
+        let fit = Polynomial::fit(&cubes).unwrap();
+        assert_eq!(fit.value_at(1 << 40), Ok(1 << 120));
+        assert_eq!(fit.value_at(1 << 43), Err(Error::Overflow));
+        assert_eq!(fit.value_at(i64::MIN), Err(Error::Overflow));
+    }
+}
@@ -1,11 +1,23 @@
+use std::env;
 use std::io::{BufReader, stdin};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use day10::parse_input;
 
 fn main() -> Result<()> {
+    let check = env::args().skip(1).any(|arg| arg == "--check");
+
     let map = parse_input(BufReader::new(stdin()))?;
-    println!("Number of enclosed cells: {}", map.count_inside());
+    let inside = map.count_inside();
+    println!("Number of enclosed cells: {inside}");
+
+    if check {
+        let shoelace = map.enclosed_tiles_shoelace();
+        if shoelace != inside {
+            bail!("The shoelace formula gives {shoelace} enclosed cells instead");
+        }
+        println!("The shoelace formula agrees");
+    }
 
     Ok(())
 }
@@ -1,6 +1,82 @@
-use std::{io::BufRead, collections::HashSet};
+use std::io::BufRead;
 use anyhow::Result;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    fn step(self, (row, col): (usize, usize)) -> (usize, usize) {
+        match self {
+            Direction::North => (row - 1, col),
+            Direction::East => (row, col + 1),
+            Direction::South => (row + 1, col),
+            Direction::West => (row, col - 1),
+        }
+    }
+
+    fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    // The two directions a pipe connects
+    fn connections(pipe: char) -> [Direction; 2] {
+        match pipe {
+            '-' => [Direction::West, Direction::East],
+            '|' => [Direction::North, Direction::South],
+            'L' => [Direction::North, Direction::East],
+            'J' => [Direction::North, Direction::West],
+            '7' => [Direction::South, Direction::West],
+            'F' => [Direction::South, Direction::East],
+            _ => unimplemented!() // Won't happen
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathTile {
+    pub position: (usize, usize),
+    /// Where to go from here to get to the next tile in the loop
+    pub direction: Direction,
+    /// Steps from the start, going whichever way around the loop is shorter
+    pub distance: usize,
+}
+
+/// The tiles in the loop, in order, starting with the one at `S`
+#[derive(Debug, Clone)]
+pub struct LoopPath {
+    tiles: Vec<PathTile>,
+}
+
+impl LoopPath {
+    pub fn tiles(&self) -> &[PathTile] {
+        &self.tiles
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// The tile furthest away from the start. The loop always has an even
+    /// length, so there's just one
+    pub fn farthest(&self) -> &PathTile {
+        &self.tiles[self.tiles.len() / 2]
+    }
+}
+
 pub struct Map {
     rows: Vec<Vec<char>>,
     start: (usize, usize),
@@ -38,12 +114,40 @@ impl Map {
         Map { rows, start }
     }
 
-    pub fn find_furthest(&self) -> usize {
-        let mut generations = 0;
+    /// Follows the loop from the start, in one of the two possible
+    /// directions, and returns its tiles in order
+    pub fn loop_path(&self) -> LoopPath {
+        let mut positions = vec![];
+        let mut current = self.start;
+        let (srow, scol) = self.start;
+        let mut direction = Direction::connections(self.rows[srow][scol])[0];
+
+        loop {
+            positions.push((current, direction));
+            current = direction.step(current);
+            if current == self.start {
+                break
+            }
+            let (row, col) = current;
+            let [a, b] = Direction::connections(self.rows[row][col]);
+            direction = if a == direction.opposite() { b } else { a };
+        }
 
-        iterate_over_path(self.start, &self.rows, |_| { generations += 1 });
+        let length = positions.len();
+        let tiles = positions.into_iter()
+            .enumerate()
+            .map(|(n, (position, direction))| PathTile {
+                position,
+                direction,
+                distance: n.min(length - n),
+            })
+            .collect();
 
-        generations - 1
+        LoopPath { tiles }
+    }
+
+    pub fn find_furthest(&self) -> usize {
+        self.loop_path().farthest().distance
     }
 
     pub fn count_inside(&self) -> usize {
@@ -51,16 +155,29 @@ impl Map {
             .map(|_| format!("{:.<width$}", "", width = self.rows[0].len()).chars().collect::<Vec<_>>())
             .collect::<Vec<_>>();
 
-        iterate_over_path(
-            self.start,
-            &self.rows,
-            |gen: &mut Vec<(usize, usize)>| {
-                for &(row, col) in gen.iter() {
-                    map[row][col] = self.rows[row][col].to_uppercase().next().unwrap();
-                }
-            });
+        for tile in self.loop_path().tiles() {
+            let (row, col) = tile.position;
+            map[row][col] = self.rows[row][col].to_uppercase().next().unwrap();
+        }
+
+        map.iter().map(apply_raycast).sum::<usize>()
+    }
+
+    /// Counts the tiles enclosed by the loop using its area, computed with
+    /// the shoelace formula over the tiles in order, and Pick's theorem:
+    /// A = I + B/2 - 1, where B is the number of tiles in the loop
+    pub fn enclosed_tiles_shoelace(&self) -> usize {
+        let path = self.loop_path();
+        let boundary = path.len() as i64;
 
-        map.iter().map(|row| apply_raycast(row)).sum::<usize>()
+        let double_area = path.tiles().iter()
+            .zip(path.tiles().iter().cycle().skip(1))
+            .map(|(a, b)| (a.position, b.position))
+            .map(|((r1, c1), (r2, c2))| c1 as i64 * r2 as i64 - c2 as i64 * r1 as i64)
+            .sum::<i64>()
+            .abs();
+
+        ((double_area - boundary) / 2 + 1) as usize
     }
 
     pub fn print(&self) {
@@ -92,36 +209,6 @@ fn apply_raycast(row: &Vec<char>) -> usize {
     count
 }
 
-fn iterate_over_path(start: (usize, usize), map: &Vec<Vec<char>>, mut action: impl FnMut(&mut Vec<(usize, usize)>)) {
-    let mut known = HashSet::new();
-    let mut generation = vec![start];
-
-    known.insert(start);
-
-    while !generation.is_empty() {
-        action(&mut generation);
-
-        let mut new_generation = Vec::new();
-
-        for &(row, col) in generation.iter() {
-            known.insert((row, col));
-            match map[row][col] {
-                '-' => { new_generation.extend([(row, col-1), (row, col+1)]) }
-                '|' => { new_generation.extend([(row-1, col), (row+1, col)]) }
-                'L' => { new_generation.extend([(row-1, col), (row, col+1)]) }
-                'J' => { new_generation.extend([(row-1, col), (row, col-1)]) }
-                '7' => { new_generation.extend([(row+1, col), (row, col-1)]) }
-                'F' => { new_generation.extend([(row+1, col), (row, col+1)]) }
-                _ => unimplemented!() // Won't happen
-            }
-        }
-
-        let unique: HashSet<(usize, usize)> = HashSet::from_iter(new_generation.iter().copied());
-
-        generation = Vec::from_iter(unique.difference(&known).into_iter().copied());
-    }
-}
-
 fn print_map(rows: &Vec<Vec<char>>) {
     for row in rows.iter() {
         let s: String = row.iter().collect();
@@ -132,9 +219,7 @@ fn print_map(rows: &Vec<Vec<char>>) {
 pub fn parse_input<R: BufRead>(stream: R) -> Result<Map> {
     let lines: Vec<String> = stream
         .lines()
-        .map(|rl| rl.map_err(drop))
-        .flatten()
-        .collect();
+        .collect::<Result<_, _>>()?;
 
     let mut start = (0, 0);
     for (row, line) in lines.iter().enumerate() {
@@ -150,7 +235,14 @@ pub fn parse_input<R: BufRead>(stream: R) -> Result<Map> {
 
 #[cfg(test)]
 mod tests {
-    use crate::apply_raycast;
+    use crate::{apply_raycast, parse_input, Direction};
+
+    const SAMPLES: [&str; 4] = [
+        include_str!("../input.small"),
+        include_str!("../input.small.2"),
+        include_str!("../input.small.3"),
+        include_str!("../input.small.4"),
+    ];
 
     #[test]
     fn raycasting() {
@@ -163,4 +255,44 @@ mod tests {
         assert_eq!(apply_raycast(&"L--J.L7...LJF7F-7L7.".chars().collect()), 3);
         assert_eq!(apply_raycast(&"L---JF-JLJ....FJLJ..".chars().collect()), 4);
     }
+
+    #[test]
+    fn shoelace_agrees_with_raycast() {
+        let counts = SAMPLES.iter()
+            .map(|sample| {
+                let map = parse_input(sample.as_bytes()).unwrap();
+                assert_eq!(map.enclosed_tiles_shoelace(), map.count_inside());
+                map.enclosed_tiles_shoelace()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(counts, [1, 1, 8, 10]);
+    }
+
+    #[test]
+    fn loop_path() {
+        let map = parse_input(SAMPLES[0].as_bytes()).unwrap();
+        let path = map.loop_path();
+
+        // The square loop in the first sample, starting at its top-left corner
+        let positions = path.tiles().iter().map(|tile| tile.position).collect::<Vec<_>>();
+        assert_eq!(positions, [(1, 1), (2, 1), (3, 1), (3, 2), (3, 3), (2, 3), (1, 3), (1, 2)]);
+        let directions = path.tiles().iter().map(|tile| tile.direction).collect::<Vec<_>>();
+        assert_eq!(directions[..3], [Direction::South, Direction::South, Direction::East]);
+        let distances = path.tiles().iter().map(|tile| tile.distance).collect::<Vec<_>>();
+        assert_eq!(distances, [0, 1, 2, 3, 4, 3, 2, 1]);
+        assert_eq!(path.farthest().position, (3, 3));
+
+        let farthest = SAMPLES.iter()
+            .map(|sample| {
+                let path = parse_input(sample.as_bytes()).unwrap().loop_path();
+                // Every step leads to the next tile, and the last one back to the start
+                for (tile, next) in path.tiles().iter().zip(path.tiles().iter().cycle().skip(1)) {
+                    assert_eq!(tile.direction.step(tile.position), next.position);
+                }
+                path.farthest().distance
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(farthest, [4, 8, 70, 80]);
+    }
 }